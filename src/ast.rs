@@ -0,0 +1,403 @@
+use std::fmt;
+use std::mem;
+
+use crate::error::CalcError;
+use crate::operator::{Expression, Lexem, Number, Operator};
+use crate::stack::Stack;
+use crate::token::Span;
+
+///
+/// Абстрактное синтаксическое дерево выражения
+/// Каждый узел хранит положение во входной строке: для операторов и функций - положение их обозначения
+///   - Num    - число
+///   - Bool   - логический литерал
+///   - Var    - переменная
+///   - Const  - именованная константа и её значение
+///   - Unary  - унарный оператор и его аргумент
+///   - Binary - бинарный оператор и его аргументы (левый, правый)
+///   - Call   - вызов функции с аргументами
+///   - Cond   - условное выражение (условие, значение при истинном и при ложном условии)
+///
+/// У условного выражения вычисляется только выбранная ветвь
+///
+/// Дерево не расходуется при обходе, поэтому одно и то же разобранное выражение
+///   можно вычислять, печатать и анализировать многократно
+///
+#[derive(Debug)]
+pub enum Expr {
+    Num(Number, Span),
+    Bool(bool, Span),
+    Var(String, Span),
+    Const(String, Number, Span),
+    Unary(Operator, Box<Expr>, Span),
+    Binary(Operator, Box<Expr>, Box<Expr>, Span),
+    Call(Operator, Vec<Expr>, Span),
+    Cond(Box<Expr>, Box<Expr>, Box<Expr>, Span),
+}
+
+// Приоритет тернарного оператора `?:` - самый слабый, ассоциативность правая
+pub const CONDITIONAL_PRECEDENCE: u32 = 13;
+
+// Элемент печати выражения в инфиксной форме: узел дерева (в скобках или без) или фрагмент текста
+enum Piece<'a> {
+    Node(&'a Expr, bool),
+    Text(&'a str),
+}
+
+// Реализация методов дерева выражения
+// Дерево может быть сколь угодно глубоким (`1+1+...+1`), поэтому все обходы выполняются
+//   с явным стеком, а не рекурсией
+impl Expr {
+    ///
+    /// Построение дерева по выражению в обратной польской нотации
+    /// Используется стандартный алгоритм со стеком: операнды кладутся в стек,
+    ///   оператор забирает из стека свои аргументы и кладет в стек построенный узел
+    ///
+    pub fn from_rpn(mut input: Expression) -> Result<Expr, CalcError> {
+        let mut stack: Stack<Expr> = Stack::new();
+
+        while let Some((lexem, span)) = input.dequeue() {
+            let node = match lexem {
+                Lexem::NumberLex(v) => Expr::Num(v, span),
+                Lexem::BoolLex(b) => Expr::Bool(b, span),
+                Lexem::VariableLex(name) => Expr::Var(name, span),
+                Lexem::ConstantLex(name, value) => Expr::Const(name, value, span),
+                Lexem::OperatorLex(op) => match op {
+                    Operator::Unary(_) | Operator::Function(_) => {
+                        let arg = Expr::pop_operand(&mut stack, &op, span)?;
+                        match op {
+                            Operator::Function(_) => Expr::Call(op, vec![arg], span),
+                            _ => Expr::Unary(op, Box::new(arg), span),
+                        }
+                    }
+                    Operator::Binary(_) => {
+                        // на вершине стека находится правый операнд
+                        let rhs = Expr::pop_operand(&mut stack, &op, span)?;
+                        let lhs = Expr::pop_operand(&mut stack, &op, span)?;
+                        Expr::Binary(op, Box::new(lhs), Box::new(rhs), span)
+                    }
+                    _ => {
+                        return Err(CalcError::Conversion(
+                            format!("неизвестный оператор '{}'", op.symbol()),
+                            span,
+                        ))
+                    }
+                },
+                Lexem::CallLex(op, count) => {
+                    let mut args: Vec<Expr> = Vec::with_capacity(count);
+                    for _ in 0..count {
+                        args.push(Expr::pop_operand(&mut stack, &op, span)?);
+                    }
+                    args.reverse();
+                    Expr::Call(op, args, span)
+                }
+                Lexem::CondLex => {
+                    let op = Operator::Unknown("?:".to_string());
+                    let otherwise = Expr::pop_operand(&mut stack, &op, span)?;
+                    let then = Expr::pop_operand(&mut stack, &op, span)?;
+                    let cond = Expr::pop_operand(&mut stack, &op, span)?;
+                    Expr::Cond(Box::new(cond), Box::new(then), Box::new(otherwise), span)
+                }
+            };
+            stack.push(node);
+        }
+
+        match (stack.pop(), stack.pop()) {
+            (Some(result), None) => Ok(result),
+            (Some(_), Some(extra)) => Err(CalcError::Conversion(
+                "Очередь аргументов не пуста, но очередь операторов опустела".to_string(),
+                extra.extent(),
+            )),
+            _ => Err(CalcError::Conversion(
+                "пустое выражение".to_string(),
+                Span::default(),
+            )),
+        }
+    }
+
+    ///
+    /// Положение узла во входной строке
+    ///
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Num(_, span)
+            | Expr::Bool(_, span)
+            | Expr::Var(_, span)
+            | Expr::Const(_, _, span)
+            | Expr::Unary(_, _, span)
+            | Expr::Binary(_, _, _, span)
+            | Expr::Call(_, _, span)
+            | Expr::Cond(_, _, _, span) => *span,
+        }
+    }
+
+    ///
+    /// Непосредственные потомки узла в порядке записи
+    ///
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Num(..) | Expr::Bool(..) | Expr::Var(..) | Expr::Const(..) => Vec::new(),
+            Expr::Unary(_, arg, _) => vec![arg],
+            Expr::Binary(_, lhs, rhs, _) => vec![lhs, rhs],
+            Expr::Call(_, args, _) => args.iter().collect(),
+            Expr::Cond(cond, then, otherwise, _) => vec![cond, then, otherwise],
+        }
+    }
+
+    ///
+    /// Свертка дерева: функция вызывается для каждого узла после его потомков
+    ///   и получает узел вместе с результатами для его потомков в порядке записи
+    ///
+    pub fn fold<T>(&self, mut visit: impl FnMut(&Expr, Vec<T>) -> T) -> T {
+        // узлы, потомки которых еще не обработаны, и результаты обработанных узлов
+        let mut work: Vec<(&Expr, bool)> = vec![(self, false)];
+        let mut results: Vec<T> = Vec::new();
+
+        while let Some((node, expanded)) = work.pop() {
+            let children = node.children();
+            if expanded {
+                let args = results.split_off(results.len() - children.len());
+                results.push(visit(node, args));
+            } else {
+                work.push((node, true));
+                work.extend(children.into_iter().rev().map(|child| (child, false)));
+            }
+        }
+
+        results.pop().expect("результат свертки корня дерева")
+    }
+
+    ///
+    /// Фрагмент входной строки, покрывающий узел вместе со всеми его потомками
+    ///
+    pub fn extent(&self) -> Span {
+        self.fold(|node, spans: Vec<Span>| spans.into_iter().fold(node.span(), |s, span| s.join(span)))
+    }
+
+    ///
+    /// Запись выражения в обратной польской нотации (обход дерева в глубину, корень последним)
+    ///
+    pub fn to_rpn(&self) -> String {
+        let mut output: Vec<String> = Vec::new();
+        self.fold(|node, _: Vec<()>| {
+            output.push(match node {
+                Expr::Num(v, _) => v.to_string(),
+                Expr::Bool(b, _) => b.to_string(),
+                Expr::Var(name, _) | Expr::Const(name, _, _) => name.clone(),
+                Expr::Unary(op, _, _) | Expr::Binary(op, _, _, _) | Expr::Call(op, _, _) => op.name().to_string(),
+                Expr::Cond(..) => "?:".to_string(),
+            })
+        });
+        output.join(" ")
+    }
+
+    ///
+    /// Разбор узла на потомков в порядке записи
+    ///
+    pub fn into_children(mut self) -> Vec<Expr> {
+        let mut children = Vec::new();
+        self.take_children(&mut children);
+        children
+    }
+
+    // Копия узла с заданными потомками
+    fn with_children(&self, mut children: Vec<Expr>) -> Expr {
+        let mut next = || Box::new(children.remove(0));
+        match self {
+            Expr::Num(v, span) => Expr::Num(v.clone(), *span),
+            Expr::Bool(b, span) => Expr::Bool(*b, *span),
+            Expr::Var(name, span) => Expr::Var(name.clone(), *span),
+            Expr::Const(name, value, span) => Expr::Const(name.clone(), value.clone(), *span),
+            Expr::Unary(op, _, span) => Expr::Unary(op.clone(), next(), *span),
+            Expr::Binary(op, _, _, span) => Expr::Binary(op.clone(), next(), next(), *span),
+            Expr::Cond(_, _, _, span) => Expr::Cond(next(), next(), next(), *span),
+            Expr::Call(op, _, span) => Expr::Call(op.clone(), children, *span),
+        }
+    }
+
+    // Перенос потомков узла в список, узел остается без потомков
+    fn take_children(&mut self, output: &mut Vec<Expr>) {
+        // на место потомка ставится лист, не владеющий другими узлами
+        let mut take = |child: &mut Box<Expr>| {
+            output.push(mem::replace(child.as_mut(), Expr::Bool(false, Span::default())));
+        };
+        match self {
+            Expr::Num(..) | Expr::Bool(..) | Expr::Var(..) | Expr::Const(..) => {}
+            Expr::Unary(_, arg, _) => take(arg),
+            Expr::Binary(_, lhs, rhs, _) => {
+                take(lhs);
+                take(rhs);
+            }
+            Expr::Call(_, args, _) => output.append(args),
+            Expr::Cond(cond, then, otherwise, _) => {
+                take(cond);
+                take(then);
+                take(otherwise);
+            }
+        }
+    }
+
+    // Извлечение операнда оператора из стека строящегося дерева
+    fn pop_operand(stack: &mut Stack<Expr>, op: &Operator, span: Span) -> Result<Expr, CalcError> {
+        stack.pop().ok_or_else(|| {
+            CalcError::Conversion(
+                format!("недостаточно аргументов для оператора {}", op.symbol()),
+                span,
+            )
+        })
+    }
+
+    // Нужны ли скобки при печати операнда в инфиксной форме
+    // Скобки нужны, если операнд связывается слабее родительского оператора, либо
+    //   при равном приоритете находится не на той стороне, с которой ассоциативен оператор
+    fn needs_parens(&self, parent: (u32, bool), is_right: bool) -> bool {
+        let (priority, is_left) = parent;
+        let own = match self {
+            Expr::Unary(op, _, _) | Expr::Binary(op, _, _, _) => op.precedence().map(|(p, _)| p),
+            Expr::Cond(..) => Some(CONDITIONAL_PRECEDENCE),
+            _ => None,
+        };
+
+        match own {
+            Some(p) => p > priority || (p == priority && is_right == is_left),
+            None => false,
+        }
+    }
+}
+
+// Копирование дерева сверткой, без рекурсии
+impl Clone for Expr {
+    fn clone(&self) -> Self {
+        self.fold(|node, children| node.with_children(children))
+    }
+}
+
+// Удаление дерева без рекурсии: потомки переносятся в список и удаляются по одному
+impl Drop for Expr {
+    fn drop(&mut self) {
+        let mut nodes: Vec<Expr> = Vec::new();
+        self.take_children(&mut nodes);
+        while let Some(mut node) = nodes.pop() {
+            node.take_children(&mut nodes);
+        }
+    }
+}
+
+// Печать выражения в инфиксной форме с минимально необходимым количеством скобок
+// Фрагменты печатаются из стека: узел заменяется в стеке фрагментами своей записи
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut pieces: Vec<Piece> = vec![Piece::Node(self, false)];
+
+        while let Some(piece) = pieces.pop() {
+            let (node, parens) = match piece {
+                Piece::Text(text) => {
+                    f.write_str(text)?;
+                    continue;
+                }
+                Piece::Node(node, parens) => (node, parens),
+            };
+
+            // фрагменты записи узла в порядке печати
+            let mut parts: Vec<Piece> = Vec::new();
+            if parens {
+                parts.push(Piece::Text("("));
+            }
+            match node {
+                Expr::Num(v, _) => write!(f, "{}", v)?,
+                Expr::Bool(b, _) => write!(f, "{}", b)?,
+                Expr::Var(name, _) | Expr::Const(name, _, _) => f.write_str(name)?,
+                // Постфиксный оператор над постфиксным оператором записывается со скобками: `(3!)!` не является `3!!`
+                Expr::Unary(op, arg, _) if op.is_postfix() => {
+                    let nested = matches!(arg.as_ref(), Expr::Unary(inner, _, _) if inner.is_postfix());
+                    let parent = op.precedence().unwrap_or((0, true));
+                    parts.push(Piece::Node(arg, nested || arg.needs_parens(parent, false)));
+                    parts.push(Piece::Text(op.symbol()));
+                }
+                Expr::Unary(op, arg, _) => {
+                    let parent = op.precedence().unwrap_or((0, true));
+                    parts.push(Piece::Text(op.symbol()));
+                    parts.push(Piece::Node(arg, arg.needs_parens(parent, true)));
+                }
+                Expr::Binary(op, lhs, rhs, _) => {
+                    let parent = op.precedence().unwrap_or((0, true));
+                    parts.push(Piece::Node(lhs, lhs.needs_parens(parent, false)));
+                    parts.extend([Piece::Text(" "), Piece::Text(op.symbol()), Piece::Text(" ")]);
+                    parts.push(Piece::Node(rhs, rhs.needs_parens(parent, true)));
+                }
+                Expr::Call(op, args, _) => {
+                    parts.extend([Piece::Text(op.symbol()), Piece::Text("(")]);
+                    for (ind, arg) in args.iter().enumerate() {
+                        if ind > 0 {
+                            parts.push(Piece::Text(", "));
+                        }
+                        parts.push(Piece::Node(arg, false));
+                    }
+                    parts.push(Piece::Text(")"));
+                }
+                Expr::Cond(cond, then, otherwise, _) => {
+                    parts.push(Piece::Node(cond, cond.needs_parens((CONDITIONAL_PRECEDENCE, false), false)));
+                    parts.extend([Piece::Text(" ? "), Piece::Node(then, false), Piece::Text(" : ")]);
+                    let parens = otherwise.needs_parens((CONDITIONAL_PRECEDENCE, false), true);
+                    parts.push(Piece::Node(otherwise, parens));
+                }
+            }
+            if parens {
+                parts.push(Piece::Text(")"));
+            }
+            pieces.extend(parts.into_iter().rev());
+        }
+
+        Ok(())
+    }
+}
+
+// Базовые тесты
+#[cfg(test)]
+use crate::converters::{Converter, InfixToAst};
+#[cfg(test)]
+use crate::token::{token_list, TokenType};
+
+#[test]
+fn test_ast_rpn_and_infix() {
+    // (1-2)-(3-4)*max(5,-6)
+    let test_tokens = token_list(&[
+        (TokenType::OpenedParenthesis, "("),
+        (TokenType::NumberInt, "1"),
+        (TokenType::BinaryOperator, "-"),
+        (TokenType::NumberInt, "2"),
+        (TokenType::ClosedParenthesis, ")"),
+        (TokenType::BinaryOperator, "-"),
+        (TokenType::OpenedParenthesis, "("),
+        (TokenType::NumberInt, "3"),
+        (TokenType::BinaryOperator, "-"),
+        (TokenType::NumberInt, "4"),
+        (TokenType::ClosedParenthesis, ")"),
+        (TokenType::BinaryOperator, "*"),
+        (TokenType::Function, "max"),
+        (TokenType::OpenedParenthesis, "("),
+        (TokenType::NumberInt, "5"),
+        (TokenType::ArgumentSeparator, ","),
+        (TokenType::UnaryOperator, "-"),
+        (TokenType::NumberInt, "6"),
+        (TokenType::ClosedParenthesis, ")"),
+    ]);
+
+    let expr = InfixToAst::new().convert(test_tokens).unwrap();
+
+    assert_eq!(expr.to_rpn(), "1 2 - 3 4 - 5 6 NEG max × -");
+    assert_eq!(expr.to_string(), "1 - 2 - (3 - 4) * max(5, -6)");
+    assert_eq!(expr.extent(), Span::new(1, 20));
+}
+
+#[test]
+fn test_ast_deep_tree() {
+    // длинная сумма и цепочка унарных минусов обходятся без рекурсии
+    let sum = crate::parse(&format!("1{}", " + 1".repeat(20_000))).unwrap();
+    assert_eq!(sum.to_string(), format!("1{}", " + 1".repeat(20_000)));
+    assert_eq!(sum.clone().to_rpn().len(), 1 + 20_000 * 4);
+    assert_eq!(sum.extent(), Span::new(0, 80_001));
+
+    let negation = crate::parse(&format!("{}x", "-".repeat(20_000))).unwrap();
+    assert_eq!(negation.to_string(), format!("{}x", "-".repeat(20_000)));
+}
@@ -2,17 +2,33 @@ use std::rc::Rc;
 
 use crate::calculator::Calculator;
 use crate::converters::Converter;
+use crate::definitions::Definitions;
+use crate::environment::Environment;
+use crate::error::CalcError;
+use crate::functions::{AngleUnit, Function};
 use crate::lexer::Lexer;
+use crate::number::{Number, NumberFormat, NumericBackend};
 use crate::reader::Reader;
+use crate::registry::{OperatorDef, OperatorRegistry};
 use crate::validator::Validator;
 use crate::writer::Writer;
 
 ///
 /// Строитель калькулятора
 /// Используется для получения объекта калькулятора с заданными настройками
+/// Ошибки регистрации операторов и функций накапливаются и возвращаются при сборке
 ///
 pub struct CalculatorBuilder {
     target: Calculator,
+    registry: OperatorRegistry,
+    errors: Vec<String>,
+}
+
+// Имплементация типажа для возможности создания объекта со значениями по-умолчанию
+impl Default for CalculatorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 // Реализация методов строителя
@@ -21,6 +37,8 @@ impl CalculatorBuilder {
     pub fn new() -> Self {
         Self {
             target: Calculator::new(),
+            registry: OperatorRegistry::standard(),
+            errors: Vec::new(),
         }
     }
 
@@ -59,23 +77,178 @@ impl CalculatorBuilder {
     ///
     /// Установка объекта выводящего данные. Должен реализовывать типаж Writer
     ///
-    #[allow(dead_code)]
     pub fn output_stream(&mut self, writer: Rc<dyn Writer>) -> &mut Self {
         self.target.writer = writer;
         self
     }
 
+    ///
+    /// Установка числовой системы, в которой выполняются вычисления. Должна реализовывать типаж NumericBackend
+    ///
+    pub fn backend(&mut self, backend: Rc<dyn NumericBackend>) -> &mut Self {
+        self.target.backend = backend;
+        self
+    }
+
+    ///
+    /// Установка настроек представления результатов (например, десятичного приближения дробей)
+    ///
+    pub fn number_format(&mut self, format: NumberFormat) -> &mut Self {
+        self.target.format = format;
+        self
+    }
+
+    ///
+    /// Включение режима трассировки: вывод таблиц шагов преобразования и вычисления каждого выражения
+    ///
+    pub fn trace(&mut self, enabled: bool) -> &mut Self {
+        self.target.trace = enabled;
+        self
+    }
+
+    ///
+    /// Режим вывода только обратной польской нотации выражения без его вычисления
+    ///
+    pub fn rpn_only(&mut self, enabled: bool) -> &mut Self {
+        self.target.rpn_only = enabled;
+        self
+    }
+
+    ///
+    /// Установка единиц измерения углов для тригонометрических функций
+    ///
+    pub fn angle_unit(&mut self, unit: AngleUnit) -> &mut Self {
+        self.target.angle_unit = unit;
+        self
+    }
+
+    ///
+    /// Ограничение глубины вложенных вызовов пользовательских функций, в том числе рекурсивных
+    /// Кадры вызовов хранятся в явном стеке вычисления, а не в стеке потока, поэтому ограничение
+    ///   задает наибольшее число одновременно открытых кадров и прерывает бесконечную рекурсию
+    ///
+    pub fn recursion_limit(&mut self, depth: usize) -> &mut Self {
+        self.target.recursion_limit = depth;
+        self
+    }
+
+    ///
+    /// Регистрация пользовательского оператора
+    /// Конфликт с уже известным оператором или функцией является ошибкой сборки калькулятора
+    ///
+    pub fn operator(&mut self, def: OperatorDef) -> &mut Self {
+        if let Err(why) = self.registry.add_operator(def) {
+            self.errors.push(why);
+        }
+        self
+    }
+
+    ///
+    /// Регистрация пользовательской функции
+    /// Конфликт с уже известной функцией или оператором является ошибкой сборки калькулятора
+    ///
+    pub fn function(&mut self, name: &str, f: Function) -> &mut Self {
+        if let Err(why) = self.registry.add_function(name, f) {
+            self.errors.push(why);
+        }
+        self
+    }
+
+    ///
+    /// Регистрация константы, либо переопределение значения встроенной константы (например, `g`)
+    /// Конфликт с уже известной функцией или оператором является ошибкой сборки калькулятора
+    ///
+    pub fn constant(&mut self, name: &str, value: Number) -> &mut Self {
+        if let Err(why) = self.registry.set_constant(name, value) {
+            self.errors.push(why);
+        }
+        self
+    }
+
     ///
     /// Сборка калькулятора
+    /// Разборщик и преобразователь, зависящие от набора операторов, настраиваются на операторы, функции и константы строителя
+    /// В случае ошибок регистрации операторов и функций возвращается ошибка настройки
     ///
-    pub fn build(&self, hello: &str) -> Calculator {
-        Calculator {
+    pub fn build(&self, hello: &str) -> Result<Calculator, CalcError> {
+        if !self.errors.is_empty() {
+            return Err(CalcError::Config(self.errors.join("; ")));
+        }
+
+        let registry = Rc::new(self.registry.clone());
+        let lexer = self.target.lexer.with_operators(&registry);
+        let converter = self.target.converter.with_operators(&registry);
+
+        Ok(Calculator {
             hello_str: hello.to_string(),
             input: self.target.input.clone(),
-            lexer: self.target.lexer.clone(),
+            lexer: lexer.unwrap_or_else(|| self.target.lexer.clone()),
             validator: self.target.validator.clone(),
-            converter: self.target.converter.clone(),
+            converter: converter.unwrap_or_else(|| self.target.converter.clone()),
             writer: self.target.writer.clone(),
-        }
+            backend: self.target.backend.clone(),
+            format: self.target.format.clone(),
+            radix: None,
+            env: Environment::new(),
+            definitions: Definitions::new(),
+            recursion_limit: self.target.recursion_limit,
+            trace: self.target.trace,
+            rpn_only: self.target.rpn_only,
+            angle_unit: self.target.angle_unit,
+        })
     }
 }
+
+// Базовые тесты
+#[cfg(test)]
+use crate::registry::Associativity;
+
+#[test]
+fn test_build_custom_operators() {
+    let scale = 10;
+    let mut builder = crate::default_builder();
+    builder
+        .operator(OperatorDef::binary("<>", "avg", 4, Associativity::Left, |(x, y)| {
+            x.add(y)?.div(crate::Number::parse("2")?)
+        }))
+        .operator(OperatorDef::binary("rem", "rem", 3, Associativity::Left, |(x, y)| x.rem(y)))
+        .function("scaled", Function::unary(move |x| x.mul(crate::Number::parse(&scale.to_string())?)));
+
+    let mut calc = builder.build("").unwrap();
+    let result = |calc: &mut Calculator, input: &str| calc.evaluate_line(input).unwrap().1.to_string();
+
+    assert_eq!(result(&mut calc, "1 <> 2*2"), "2.5");
+    assert_eq!(result(&mut calc, "17 rem 5"), "2");
+    assert_eq!(result(&mut calc, "scaled(1.5) + 1"), "16");
+}
+
+#[test]
+fn test_build_constants() {
+    let mut builder = crate::default_builder();
+    builder
+        .constant("g", Number::parse("9.81").unwrap())
+        .constant("G", Number::parse("6.674e-11").unwrap());
+
+    let mut calc = builder.build("").unwrap();
+    let result = |calc: &mut Calculator, input: &str| calc.evaluate_line(input).map(|(_, v)| v.to_string());
+
+    assert_eq!(result(&mut calc, "2*g"), Ok("19.62".to_string()));
+    assert_eq!(result(&mut calc, "G*1e11"), Ok("6.674".to_string()));
+    assert_eq!(result(&mut calc, "round(pi*1000) + c/c"), Ok("3143".to_string()));
+    assert_eq!(result(&mut calc, "pi = 3"), Ok("3".to_string()));
+    assert_eq!(result(&mut calc, "pi*g"), Ok("29.43".to_string()));
+
+    assert!(crate::default_builder().constant("max", Number::Float(0.0)).build("").is_err());
+}
+
+#[test]
+fn test_build_conflicts() {
+    let mut builder = crate::default_builder();
+    builder
+        .operator(OperatorDef::binary("*", "times", 3, Associativity::Left, |(x, y)| x.mul(y)))
+        .function("max", Function::variadic(|_| Err("".to_string())));
+
+    let error = builder.build("").err().unwrap();
+    assert!(matches!(error, CalcError::Config(_)));
+    assert!(error.to_string().contains("'*'") && error.to_string().contains("'max'"));
+}
@@ -1,12 +1,67 @@
+use std::collections::HashMap;
 use std::rc::Rc;
 
+use crate::ast::Expr;
 use crate::converters::{Converter, EmptyConverter};
+use crate::definitions::{Definitions, UserFunction};
+use crate::derivative::differentiate;
+use crate::environment::{Environment, LAST_RESULT};
+use crate::error::CalcError;
+use crate::functions::AngleUnit;
 use crate::lexer::{EmptyLexer, Lexer};
-use crate::operator::{Expression, Lexem, Number, Operator};
+use crate::number::{FloatBackend, NumberFormat, NumericBackend};
+use crate::operator::{BaseOperator, Number, Operator};
 use crate::reader::{EmptyInput, Reader};
-use crate::stack::Stack;
+use crate::registry::PERCENT_OPERATOR;
+use crate::rules::is_definition_header;
+use crate::token::{literal_radix, Span, Token, TokenList, TokenType};
+use crate::trace::Trace;
 use crate::validator::Validator;
+use crate::value::Value;
 use crate::writer::{ConsoleOutput, Writer};
+use crate::DEFAULT_RECURSION_LIMIT;
+
+// Команда вывода списка пользовательских функций
+pub const LIST_FUNCTIONS_COMMAND: &str = ":functions";
+
+// Кадр вычисления: значения параметров пользовательской функции
+#[derive(Default)]
+struct Frame {
+    locals: HashMap<String, Value>,
+}
+
+// Задача вычисления дерева выражения. Задачи, кроме Eval, выполняются после вычисления операндов узла
+//   - Eval    - вычисление узла
+//   - Unary   - применение унарного оператора
+//   - Rhs     - левый операнд вычислен: вычисление правого, либо завершение по короткой схеме
+//   - Percent - процент от левого операнда суммы или разности
+//   - Binary  - применение бинарного оператора
+//   - Choose  - условие вычислено: вычисление выбранной ветви
+//   - Cond    - завершение условного выражения
+//   - Call    - вызов встроенной функции с заданным количеством аргументов
+//   - Invoke  - вызов пользовательской функции с заданным количеством аргументов
+//   - Return  - возврат из пользовательской функции
+enum Task<'a> {
+    Eval(&'a Expr),
+    Unary(&'a Expr, &'a BaseOperator<Value, Value>),
+    Rhs(&'a Expr, &'a BaseOperator<(Value, Value), Value>, &'a Expr),
+    Percent(&'a Expr),
+    Binary(&'a Expr, &'a BaseOperator<(Value, Value), Value>),
+    Choose(&'a Expr, &'a Expr, &'a Expr),
+    Cond(&'a Expr),
+    Call(&'a Expr, &'a Operator, usize),
+    Invoke(&'a Expr, &'a UserFunction, usize),
+    Return(&'a Expr, usize),
+}
+
+// Состояние вычисления дерева: стек задач, стек значений и кадры вызовов пользовательских функций
+// Первый кадр относится к самому выражению, на вызов из него указывают ошибки в теле функции
+struct Evaluation<'a> {
+    tasks: Vec<Task<'a>>,
+    values: Vec<Value>,
+    frames: Vec<Frame>,
+    outer_call: Option<(&'a str, Span)>,
+}
 
 ///
 /// Объект калькулятора содержащий необходимые для работы объекты
@@ -18,6 +73,22 @@ pub struct Calculator {
     pub validator: Rc<Validator>,
     pub converter: Rc<dyn Converter>,
     pub writer: Rc<dyn Writer>,
+    pub backend: Rc<dyn NumericBackend>,
+    pub format: NumberFormat,
+    pub radix: Option<u32>, // система счисления литералов последнего выражения, в ней же выводится результат
+    pub env: Environment,
+    pub definitions: Definitions, // пользовательские функции сеанса
+    pub recursion_limit: usize,   // допустимая глубина вложенных вызовов пользовательских функций
+    pub trace: bool,
+    pub rpn_only: bool,
+    pub angle_unit: AngleUnit,
+}
+
+// Имплементация типажа для возможности создания объекта со значениями по-умолчанию
+impl Default for Calculator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Реализация методов объекта калькулятора
@@ -31,161 +102,911 @@ impl Calculator {
             validator: Rc::new(Validator::new()),
             converter: Rc::new(EmptyConverter {}),
             writer: Rc::new(ConsoleOutput::default()),
+            backend: Rc::new(FloatBackend {}),
+            format: NumberFormat::default(),
+            radix: None,
+            env: Environment::new(),
+            definitions: Definitions::new(),
+            recursion_limit: DEFAULT_RECURSION_LIMIT,
+            trace: false,
+            rpn_only: false,
+            angle_unit: AngleUnit::Radians,
         }
     }
 
-    /// Выполнение процедуры вычислений
-    pub fn run(&mut self) {
+    ///
+    /// Выполнение процедуры вычислений для одной входной строки
+    /// Возвращает None, если входные данные закончились, иначе признак успешности вычисления
+    ///
+    pub fn run(&mut self) -> Option<bool> {
         println!("{}", self.hello_str);
 
         // Получение входной строки
-        let mut input_string = match self.input.read() {
-            Ok(result) => result,
+        match self.input.read() {
+            Ok(Some(input_string)) => Some(self.process(&input_string)),
+            Ok(None) => None,
             Err(why) => {
-                self.writer
-                    .print_error(format!("Ошибка получения входной строки: {}", why));
-                return;
+                self.writer.print_calc_error("", &CalcError::Read(why));
+                Some(false)
             }
-        };
+        }
+    }
 
-        // Разбор на токены (лексемы)
-        let tokens = match self.lexer.tokenize(&mut input_string) {
-            Ok(result) => result,
-            Err(why) => {
-                self.writer.print_error(format!(
-                    "Ошибка разбиения на лексемы:\n{0}\n {2:>1$} неизвестная лексема!",
-                    input_string,
-                    input_string.find(why).unwrap(),
-                    "^"
-                ));
-                return;
+    ///
+    /// Пакетная обработка: вычисление всех строк входных данных до их окончания
+    /// Возвращает true, если все строки были успешно вычислены
+    ///
+    pub fn run_batch(&mut self) -> bool {
+        let mut success = true;
+        loop {
+            match self.input.read() {
+                Ok(Some(input_string)) => success &= self.process(&input_string),
+                Ok(None) => return success,
+                Err(why) => {
+                    self.writer.print_calc_error("", &CalcError::Read(why));
+                    return false;
+                }
             }
-        };
+        }
+    }
 
-        // Валидация по установленным правилам
-        let valid_tokens = match self.validator.validate(tokens) {
-            Ok(result) => result,
+    // Вычисление и вывод результата, либо ошибки с указанием ошибочного фрагмента
+    // Если калькулятор выводит только обратную польскую нотацию, то выражение не вычисляется
+    // Определения пользовательских функций запоминаются в любом режиме, производные печатаются без вычисления
+    fn process(&mut self, input_string: &str) -> bool {
+        if input_string.trim() == LIST_FUNCTIONS_COMMAND {
+            self.list_functions();
+            return true;
+        }
+
+        match self.define_line(input_string) {
+            Ok(Some(definition)) => {
+                self.writer.print_success(format!("Определена функция: {}", definition));
+                return true;
+            }
+            Ok(None) => {}
             Err(why) => {
+                self.writer.print_calc_error(input_string, &why);
+                return false;
+            }
+        }
+
+        match self.derive_line(input_string) {
+            Ok(Some(derivative)) if self.rpn_only => {
+                self.writer.print_rpn(&derivative.to_rpn());
+                return true;
+            }
+            Ok(Some(derivative)) => {
                 self.writer
-                    .print_error(format!("Ошибка валидации: {}", why));
-                return;
+                    .print_info(format!("Обратная польская нотация: {}", derivative.to_rpn()));
+                self.writer.print_success(format!("Производная: {}", derivative));
+                return true;
             }
-        };
+            Ok(None) => {}
+            Err(why) => {
+                self.writer.print_calc_error(input_string, &why);
+                return false;
+            }
+        }
+
+        if self.rpn_only {
+            match self.convert_line(input_string) {
+                Ok(rpn) => self.writer.print_rpn(&rpn),
+                Err(why) => {
+                    self.writer.print_calc_error(input_string, &why);
+                    return false;
+                }
+            }
+            return true;
+        }
 
-        // Преобразование входной последовательности токенов
-        let mut expr = match self.converter.convert(valid_tokens) {
-            Ok(result) => result,
+        match self.evaluate_line(input_string) {
+            Ok((name, res)) => {
+                let format = NumberFormat {
+                    radix: self.radix.or(self.format.radix),
+                    ..self.format.clone()
+                };
+                self.writer.print_result(name.as_deref(), &res, &format)
+            }
             Err(why) => {
+                self.writer.print_calc_error(input_string, &why);
+                return false;
+            }
+        }
+
+        true
+    }
+
+    ///
+    /// Выполнение всех этапов обработки входной строки: разбор на токены, валидация,
+    ///   преобразование и вычисление
+    /// Результат сохраняется в окружении. Если выражение является присваиванием,
+    ///   то вместе с результатом возвращается имя переменной
+    ///
+    pub fn evaluate_line(&mut self, input: &str) -> Result<(Option<String>, Value), CalcError> {
+        let (target, valid_tokens) = self.prepare(input)?;
+        self.radix = literal_radix(&valid_tokens);
+
+        // Преобразование входной последовательности токенов в дерево выражения и вычисление выражения по дереву
+        // При включенной трассировке шаги записываются в журнал, который выводится
+        //   и в случае ошибки - до шага, на котором она произошла
+        let mut trace = if self.trace { Some(Trace::new()) } else { None };
+        let res = self.convert(valid_tokens, trace.as_mut()).and_then(|expr| match trace.as_mut() {
+            Some(trace) => self.calculate_traced(&expr, trace),
+            None => {
                 self.writer
-                    .print_error(format!("Ошибка преобразования: {}", why));
-                return;
+                    .print_info(format!("Обратная польская нотация: {}", expr.to_rpn()));
+                self.calculate(&expr)
             }
+        });
+        if let Some(trace) = &trace {
+            self.writer.print_trace(trace);
+        }
+        let res = res?;
+
+        // Сохранение результата в окружении
+        self.env.set(LAST_RESULT, res.clone());
+        if let Some(name) = &target {
+            self.env.set(name, res.clone());
+        }
+
+        Ok((target, res))
+    }
+
+    ///
+    /// Определение пользовательской функции по строке вида `имя(параметры) = выражение`
+    /// Новое определение заменяет прежнее с тем же именем, встроенные функции не переопределяются
+    /// Возвращает None, если строка не является определением функции, иначе текст определения
+    ///
+    pub fn define_line(&mut self, input: &str) -> Result<Option<String>, CalcError> {
+        let mut body = self.validator.validate(self.tokenize(input)?)?;
+        let assignment = match body.iter().position(|tok| tok.0 == TokenType::Assignment) {
+            Some(ind) if is_definition_header(&body[..ind]) => ind,
+            _ => return Ok(None),
         };
 
-        // Вычисление выражения по преобразованной последовательности
-        let res = match self.calculate(&mut expr) {
-            Ok(result) => result,
-            Err(why) => {
-                self.writer
-                    .print_error(format!("Ошибка вычисления : {}", why));
-                return;
+        for (why, span) in self.validator.warnings(&body) {
+            let fragment = input.get(span.start..span.end).unwrap_or("");
+            self.writer.print_warninig(format!("Предупреждение: {} ({})", why, fragment));
+        }
+
+        let mut header: TokenList = body.drain(..=assignment).collect();
+        header.pop();
+
+        let name = header[0].clone();
+        let params: Vec<&Token> = header.iter().filter(|tok| tok.0 == TokenType::Identifier).collect();
+        for (ind, param) in params.iter().enumerate() {
+            if params[..ind].iter().any(|prev| prev.1 == param.1) {
+                return Err(CalcError::Validation(
+                    format!("параметр '{}' указан несколько раз", param.1),
+                    param.2,
+                ));
             }
+        }
+        let params: Vec<String> = params.into_iter().map(|tok| tok.1.clone()).collect();
+
+        // Заголовок, который преобразователь распознает как вызов встроенной функции, не является определением
+        if !matches!(self.convert(header, None)?, Expr::Call(Operator::Unknown(_), _, _)) {
+            return Err(CalcError::Validation(
+                format!("встроенная функция '{}' не может быть переопределена", name.1),
+                name.2,
+            ));
+        }
+
+        let function = UserFunction {
+            name: name.1,
+            params,
+            body: Rc::new(self.convert(body, None)?),
         };
+        let definition = function.to_string();
+        self.definitions.define(function);
 
-        // Вывод результата
-        self.writer
-            .print_success(format!("\nРезультат выражения: {}", res));
+        Ok(Some(definition))
     }
 
     ///
-    /// Вычисление выражения для обратной польской нотации
-    /// Использует стандартный алгоритм с использованием стека
+    /// Символьная производная по строке вида `d/dx(выражение)`, где `x` - переменная дифференцирования
+    /// Возвращает None, если строка не является записью производной, иначе упрощенное дерево производной
+    /// Если `d` или `dx` является переменной или функцией сеанса, строка является обычным выражением
     ///
-    pub fn calculate(&self, input: &mut Expression) -> Result<Number, &str> {
-        let mut arguments_stack: Stack<Number> = Stack::new();
+    pub fn derive_line(&self, input: &str) -> Result<Option<Expr>, CalcError> {
+        let tokens = self.tokenize(input)?;
+        let is_user_name = |name: &str| self.env.get(name).is_some() || self.definitions.get(name).is_some();
+        let is_derivative = tokens.len() > 3
+            && tokens[0].0 == TokenType::Identifier
+            && tokens[0].1 == "d"
+            && tokens[1].0 == TokenType::BinaryOperator
+            && tokens[1].1 == "/"
+            && tokens[2].1.len() > 1
+            && tokens[2].1.starts_with('d')
+            && tokens[3].0 == TokenType::OpenedParenthesis
+            && !is_user_name(&tokens[0].1)
+            && !is_user_name(&tokens[2].1);
+        if !is_derivative {
+            return Ok(None);
+        }
 
-        while let Some(lexem) = input.dequeue() {
-            match lexem {
-                Lexem::NumberLex(v) => {
-                    print!("{} ", v);
-                    arguments_stack.push(v);
-                }
+        // Запись разбирается как деление `d` на вызов функции `dx`, которое должно охватывать всю строку
+        let (_, valid_tokens) = self.prepare(input)?;
+        let tree = self.convert(valid_tokens, None)?;
+        let operand = match &tree {
+            Expr::Binary(op, lhs, rhs, _) if op.name() == "/" && matches!(**lhs, Expr::Var(..)) => match rhs.as_ref() {
+                Expr::Call(Operator::Unknown(name), args, _) if args.len() == 1 => Some((name, &args[0])),
+                _ => None,
+            },
+            _ => None,
+        };
 
-                Lexem::OperatorLex(op) => {
-                    match op {
-                        Operator::Unary(op) => {
-                            print!("{} ", op.name);
-                            if let Some(arg) = arguments_stack.pop() {
-                                let result = Some((op.apply)(arg));
-                                arguments_stack.push(result.unwrap());
-                                continue;
-                            }
-
-                            return Err("error ");
-                        }
-                        Operator::Binary(op) => {
-                            print!("{} ", op.name);
-                            if let Some(arg) = arguments_stack.pop() {
-                                if let Some(arg2) = arguments_stack.pop() {
-                                    let result = Some((op.apply)((arg, arg2)));
-                                    arguments_stack.push(result.unwrap());
-                                    continue;
-                                }
-                            }
-
-                            return Err("error ");
-                        }
-                        _ => continue,
-                    };
-                }
+        match operand {
+            Some((name, expr)) => differentiate(expr, &name[1..]).map(Some),
+            None => Err(CalcError::Validation(
+                "производная записывается в виде d/dx(выражение)".to_string(),
+                tokens[0].2.join(tokens[2].2),
+            )),
+        }
+    }
+
+    ///
+    /// Вывод определений пользовательских функций сеанса
+    ///
+    pub fn list_functions(&self) {
+        let mut empty = true;
+        for function in self.definitions.list() {
+            self.writer.print_success(function.to_string());
+            empty = false;
+        }
+        if empty {
+            self.writer.print_info("Пользовательские функции не определены".to_string());
+        }
+    }
+
+    ///
+    /// Разбор входной строки в дерево выражения без вычисления
+    /// Для присваивания возвращается дерево правой части
+    ///
+    pub fn parse_line(&self, input: &str) -> Result<Expr, CalcError> {
+        let (_, valid_tokens) = self.prepare(input)?;
+        self.convert(valid_tokens, None)
+    }
+
+    ///
+    /// Преобразование входной строки в запись обратной польской нотации без вычисления
+    ///
+    pub fn convert_line(&self, input: &str) -> Result<String, CalcError> {
+        let (_, valid_tokens) = self.prepare(input)?;
+
+        let mut trace = if self.trace { Some(Trace::new()) } else { None };
+        let expr = self.convert(valid_tokens, trace.as_mut());
+        if let Some(trace) = &trace {
+            self.writer.print_trace(trace);
+        }
+
+        Ok(expr?.to_rpn())
+    }
+
+    // Разбор на токены. Переменные и пользовательские функции скрывают одноименные константы: имя константы
+    //   разбирается как имя переменной, если переменная задана или ей присваивается значение, если оно является
+    //   именем или параметром определяемой функции, а также в вызове заданной пользовательской функции
+    // Вне комплексной числовой системы имя `i` является не мнимой единицей, а именем переменной
+    fn tokenize(&self, input: &str) -> Result<TokenList, CalcError> {
+        let mut tokens = self.lexer.tokenize(input)?;
+        let assignment = tokens.iter().position(|tok| tok.0 == TokenType::Assignment);
+        let header: Vec<String> = match assignment {
+            Some(ind) if ind > 1 && tokens[1].0 == TokenType::OpenedParenthesis => {
+                tokens[..ind].iter().map(|tok| tok.1.clone()).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        for ind in 0..tokens.len() {
+            let name = tokens[ind].1.as_str();
+            let is_call = tokens.get(ind + 1).is_some_and(|next| next.0 == TokenType::OpenedParenthesis);
+            let shadowed = (ind == 0 && assignment == Some(1))
+                || header.iter().any(|param| param == name)
+                || self.env.get(name).is_some()
+                || (is_call && self.definitions.get(name).is_some());
+            let is_variable = match tokens[ind].0 {
+                TokenType::Constant => shadowed,
+                TokenType::NumberImaginary => name == "i" && !self.backend.is_complex(),
+                _ => false,
             };
+            if is_variable {
+                tokens[ind].0 = TokenType::Identifier;
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    // Разбор на токены, валидация и отделение имени переменной, если выражение является присваиванием
+    fn prepare(&self, input: &str) -> Result<(Option<String>, TokenList), CalcError> {
+        // Разбор на токены (лексемы)
+        let tokens = self.tokenize(input)?;
+
+        // Валидация по установленным правилам
+        let valid_tokens = self.validator.validate(tokens)?;
+
+        // Предупреждения о неоднозначных фрагментах выражения
+        for (why, span) in self.validator.warnings(&valid_tokens) {
+            let fragment = input.get(span.start..span.end).unwrap_or("");
+            self.writer.print_warninig(format!("Предупреждение: {} ({})", why, fragment));
+        }
+
+        Ok(Calculator::split_assignment(valid_tokens))
+    }
+
+    ///
+    /// Разделение выражения вида `имя = выражение` на имя переменной и вычисляемое выражение
+    /// Если выражение не является присваиванием, то оно возвращается без изменений
+    ///
+    fn split_assignment(mut tokens: TokenList) -> (Option<String>, TokenList) {
+        if tokens.len() > 1
+            && tokens[0].0 == TokenType::Identifier
+            && tokens[1].0 == TokenType::Assignment
+        {
+            let expr = tokens.split_off(2);
+            return (Some(tokens.remove(0).1), expr);
         }
 
-        if let Some(result) = arguments_stack.pop() {
-            if !arguments_stack.is_empty() {
-                return Err("Очередь аргументов не пуста, но очередь операторов опустела");
+        (None, tokens)
+    }
+
+    // Преобразование входной последовательности токенов в дерево выражения
+    fn convert(&self, tokens: TokenList, trace: Option<&mut Trace>) -> Result<Expr, CalcError> {
+        match trace {
+            Some(trace) => self.converter.convert_traced(tokens, trace),
+            None => self.converter.convert(tokens),
+        }
+    }
+
+    ///
+    /// Вычисление выражения, заданного деревом
+    /// Дерево обходится в глубину: сначала вычисляются аргументы, затем применяется оператор
+    /// Результатом является число, либо логическое значение (для сравнений и логических операторов)
+    /// Все числа и результаты операций приводятся к числовой системе калькулятора
+    ///
+    pub fn calculate(&self, expr: &Expr) -> Result<Value, CalcError> {
+        self.evaluate(expr, &mut None)
+    }
+
+    ///
+    /// Вычисление выражения с записью состояния стека аргументов до и после каждого шага в журнал трассировки
+    /// Порядок шагов совпадает с порядком элементов выражения в обратной польской нотации
+    ///
+    pub fn calculate_traced(&self, expr: &Expr, trace: &mut Trace) -> Result<Value, CalcError> {
+        self.evaluate(expr, &mut Some(trace))
+    }
+
+    // Вычисление обходом дерева с явными стеками задач, значений и кадров пользовательских функций,
+    //   поэтому ни глубина выражения, ни глубина рекурсии функций не расходуют стек потока
+    fn evaluate<'a>(&'a self, expr: &'a Expr, trace: &mut Option<&mut Trace>) -> Result<Value, CalcError> {
+        let mut state = Evaluation {
+            tasks: vec![Task::Eval(expr)],
+            values: Vec::new(),
+            frames: vec![Frame::default()],
+            outer_call: None,
+        };
+
+        while let Some(task) = state.tasks.pop() {
+            if let Err(why) = self.step(task, &mut state, trace) {
+                return Err(match (why, state.outer_call) {
+                    // ошибка в теле функции указывает на вызов в исходном выражении
+                    (CalcError::Evaluation(why, _), Some((name, span))) => {
+                        CalcError::Evaluation(format!("{} (в функции {})", why, name), span)
+                    }
+                    (why, _) => why,
+                });
             }
+        }
+
+        Ok(state.values.pop().unwrap())
+    }
+
+    // Выполнение одной задачи вычисления
+    // Вычисление узла откладывает применение оператора и кладет над ним в стек задач вычисление операндов,
+    //   поэтому шаги выполняются в порядке обратной польской нотации
+    fn step<'a>(
+        &'a self,
+        task: Task<'a>,
+        state: &mut Evaluation<'a>,
+        trace: &mut Option<&mut Trace>,
+    ) -> Result<(), CalcError> {
+        match task {
+            Task::Eval(expr) => {
+                let at = |why: String| CalcError::Evaluation(why, expr.span());
+                match expr {
+                    Expr::Num(v, _) | Expr::Const(_, v, _) => {
+                        self.finish(expr, Value::Number(v.clone()), None, state, trace)?
+                    }
+                    Expr::Bool(b, _) => self.finish(expr, Value::Bool(*b), None, state, trace)?,
+                    Expr::Var(name, _) => {
+                        let frame = state.frames.last().unwrap();
+                        let value = match frame.locals.get(name).or_else(|| self.env.get(name)) {
+                            Some(v) => v.clone(),
+                            None => return Err(at(format!("неизвестная переменная '{}'", name))),
+                        };
+                        self.finish(expr, value, None, state, trace)?
+                    }
+                    Expr::Unary(Operator::Unary(op), arg, _) => {
+                        state.tasks.push(Task::Unary(expr, op));
+                        state.tasks.push(Task::Eval(arg));
+                    }
+                    Expr::Binary(Operator::Binary(op), lhs, rhs, _) => {
+                        state.tasks.push(Task::Rhs(expr, op, rhs));
+                        state.tasks.push(Task::Eval(lhs));
+                    }
+                    Expr::Unary(op, _, _) | Expr::Binary(op, _, _, _) => {
+                        return Err(at(format!("неизвестный оператор '{}'", op.symbol())))
+                    }
+                    Expr::Cond(cond, then, otherwise, _) => {
+                        state.tasks.push(Task::Choose(expr, then, otherwise));
+                        state.tasks.push(Task::Eval(cond));
+                    }
+                    Expr::Call(Operator::Unknown(name), args, _) => {
+                        let function = match self.definitions.get(name) {
+                            Some(function) => function,
+                            None => return Err(at(format!("неизвестная функция '{}'", name))),
+                        };
+                        state.tasks.push(Task::Invoke(expr, function, args.len()));
+                        state.tasks.extend(args.iter().rev().map(Task::Eval));
+                    }
+                    Expr::Call(op, args, _) => {
+                        state.tasks.push(Task::Call(expr, op, args.len()));
+                        state.tasks.extend(args.iter().rev().map(Task::Eval));
+                    }
+                }
+            }
+            Task::Unary(expr, op) => {
+                let at = |why: String| CalcError::Evaluation(why, expr.span());
+                let arg = state.values.pop().unwrap();
+                self.finish(expr, (op.apply)(arg).map_err(at)?, None, state, trace)?
+            }
+            // При вычислении по короткой схеме правый операнд не вычисляется, а узел забирает одно значение
+            // В сумме и разности процент берется от левого операнда: `200 + 10%` = 200 + 200·10/100 = 220,
+            //   в остальных случаях процент является долей: `50 * 10%` = 5
+            Task::Rhs(expr, op, rhs) => match (op.short_circuit, rhs) {
+                (Some(stop), _) if state.values.last() == Some(&Value::Bool(stop)) => {
+                    let lhs = state.values.pop().unwrap();
+                    self.finish(expr, lhs, Some(1), state, trace)?
+                }
+                (_, Expr::Unary(Operator::Unary(percent), share, _))
+                    if percent.postfix && percent.name == PERCENT_OPERATOR && (op.symbol == "+" || op.symbol == "-") =>
+                {
+                    state.tasks.push(Task::Binary(expr, op));
+                    state.tasks.push(Task::Percent(rhs));
+                    state.tasks.push(Task::Eval(share));
+                }
+                _ => {
+                    state.tasks.push(Task::Binary(expr, op));
+                    state.tasks.push(Task::Eval(rhs));
+                }
+            },
+            Task::Percent(expr) => {
+                let at = |why: String| CalcError::Evaluation(why, expr.span());
+                let share = state.values.pop().unwrap().number().map_err(at)?;
+                let base = state.values.last().unwrap().clone().number().map_err(at)?;
+                let value = base.mul(share).and_then(Number::percent).map_err(at)?;
+                self.finish(expr, Value::Number(value), None, state, trace)?
+            }
+            Task::Binary(expr, op) => {
+                let at = |why: String| CalcError::Evaluation(why, expr.span());
+                let rhs = state.values.pop().unwrap();
+                let lhs = state.values.pop().unwrap();
+                self.finish(expr, (op.apply)((lhs, rhs)).map_err(at)?, None, state, trace)?
+            }
+            // Вычисляется только ветвь, выбранная условием
+            Task::Choose(expr, then, otherwise) => {
+                let at = |why: String| CalcError::Evaluation(why, expr.span());
+                let branch = match state.values.pop().unwrap().boolean().map_err(at)? {
+                    true => then,
+                    false => otherwise,
+                };
+                state.tasks.push(Task::Cond(expr));
+                state.tasks.push(Task::Eval(branch));
+            }
+            Task::Cond(expr) => {
+                let value = state.values.pop().unwrap();
+                self.finish(expr, value, Some(2), state, trace)?
+            }
+            // Функции выполняются только над числами
+            Task::Call(expr, op, count) => {
+                let at = |why: String| CalcError::Evaluation(why, expr.span());
+                let args = state.values.split_off(state.values.len() - count);
+                let mut values: Vec<Number> = Vec::with_capacity(count);
+                for arg in args {
+                    values.push(arg.number().map_err(at)?);
+                }
+                let value = self.apply_function(op, values).map_err(at)?;
+                self.finish(expr, Value::Number(value), None, state, trace)?
+            }
+            // Тело пользовательской функции вычисляется в собственном кадре с параметрами
+            Task::Invoke(expr, function, count) => {
+                if state.frames.len() == 1 {
+                    state.outer_call = Some((&function.name, expr.span()));
+                }
+                let args = state.values.split_off(state.values.len() - count);
+                let frame = self.enter(function, args, state.frames.len() - 1)?;
+                state.frames.push(frame);
+                state.tasks.push(Task::Return(expr, count));
+                state.tasks.push(Task::Eval(&function.body));
+            }
+            // Результат тела забирается из стека аргументов вместе с аргументами
+            Task::Return(expr, count) => {
+                state.frames.pop();
+                if state.frames.len() == 1 {
+                    state.outer_call = None;
+                }
+                let value = state.values.pop().unwrap();
+                self.finish(expr, value, Some(count + 1), state, trace)?
+            }
+        }
+
+        Ok(())
+    }
+
+    // Завершение вычисления узла: число приводится к числовой системе калькулятора,
+    //   шаг записывается в журнал трассировки, результат кладется в стек значений
+    // consumed - количество значений, которые узел забирает из стека аргументов, если оно отличается от числа операндов
+    fn finish(
+        &self,
+        expr: &Expr,
+        result: Value,
+        consumed: Option<usize>,
+        state: &mut Evaluation,
+        trace: &mut Option<&mut Trace>,
+    ) -> Result<(), CalcError> {
+        let result = match result {
+            Value::Number(v) => {
+                Value::Number(self.backend.normalize(v).map_err(|why| CalcError::Evaluation(why, expr.span()))?)
+            }
+            boolean => boolean,
+        };
+
+        if let Some(trace) = trace {
+            Calculator::trace_step(expr, consumed, &result, trace);
+        }
+        state.values.push(result);
+
+        Ok(())
+    }
+
+    ///
+    /// Запись шага вычисления в журнал трассировки: элемент выражения забирает из стека аргументов
+    ///   свои операнды, либо заданное количество значений, и кладет результат
+    ///
+    fn trace_step(expr: &Expr, consumed: Option<usize>, result: &Value, trace: &mut Trace) {
+        let (item, arity) = match expr {
+            Expr::Num(v, _) => (v.to_string(), 0),
+            Expr::Bool(b, _) => (b.to_string(), 0),
+            Expr::Var(name, _) | Expr::Const(name, _, _) => (name.clone(), 0),
+            Expr::Unary(op, _, _) => (op.name().to_string(), 1),
+            Expr::Binary(op, _, _, _) => (op.name().to_string(), 2),
+            Expr::Call(op, args, _) => (op.name().to_string(), args.len()),
+            Expr::Cond(..) => ("?:".to_string(), 3),
+        };
+        trace.evaluation_step(&item, consumed.unwrap_or(arity), result.to_string());
+    }
+
+    ///
+    /// Применение встроенной функции к вычисленным аргументам
+    /// Углы переводятся в радианы и обратно в соответствии с единицами измерения калькулятора
+    ///
+    fn apply_function(&self, op: &Operator, values: Vec<Number>) -> Result<Number, String> {
+        let values = self.angle_unit.convert_arguments(op.name(), values)?;
+        let result = match op {
+            Operator::Function(op) => {
+                let mut args = Calculator::check_arguments(&op.name, values, Some(1))?;
+                (op.apply)(args.remove(0))?
+            }
+            Operator::BinaryFunction(op) => {
+                let mut args = Calculator::check_arguments(&op.name, values, Some(2))?;
+                let rhs = args.pop().unwrap();
+                let lhs = args.pop().unwrap();
+                (op.apply)((lhs, rhs))?
+            }
+            Operator::VariadicFunction(op) => {
+                let args = Calculator::check_arguments(&op.name, values, None)?;
+                (op.apply)(args)?
+            }
+            _ => return Err("оператор не может быть вызван как функция".to_string()),
+        };
+        self.angle_unit.convert_result(op.name(), result)
+    }
 
-            return Ok(result);
+    ///
+    /// Вход в пользовательскую функцию: параметры связываются со значениями аргументов в новом кадре
+    /// Глубина вложенных вызовов ограничена, что прерывает бесконечную рекурсию
+    ///
+    fn enter(&self, function: &UserFunction, args: Vec<Value>, depth: usize) -> Result<Frame, CalcError> {
+        let span = function.body.span();
+        if args.len() != function.params.len() {
+            return Err(CalcError::Evaluation(
+                format!(
+                    "функция {} ожидает аргументов: {}, передано: {}",
+                    function.name,
+                    function.params.len(),
+                    args.len()
+                ),
+                span,
+            ));
+        }
+        if depth >= self.recursion_limit {
+            return Err(CalcError::Evaluation(
+                format!("превышена допустимая глубина вызовов функций: {}", self.recursion_limit),
+                span,
+            ));
         }
 
-        Err("Не удалось вычислить выражение")
+        Ok(Frame {
+            locals: function.params.iter().cloned().zip(args).collect(),
+        })
+    }
+
+    ///
+    /// Проверка количества аргументов вызова функции
+    /// Если ожидаемое количество не задано, то функция принимает любое ненулевое число аргументов
+    ///
+    fn check_arguments(name: &str, args: Vec<Number>, expected: Option<usize>) -> Result<Vec<Number>, String> {
+        match expected {
+            Some(expected) if expected != args.len() => Err(format!(
+                "функция {} ожидает аргументов: {}, передано: {}",
+                name,
+                expected,
+                args.len()
+            )),
+            None if args.is_empty() => Err(format!("функция {} ожидает хотя бы один аргумент", name)),
+            _ => Ok(args),
+        }
     }
 }
 
 // базовые тесты
 #[cfg(test)]
+use crate::operator::{Expression, Lexem};
+#[cfg(test)]
 use crate::queue::Queue;
-use crate::token::{TokenType, Token};
 
 #[test]
 fn test_calculate_simple() {
-    let mut expr: Queue<Lexem> = Queue::new();
-    expr.enqueue(Lexem::NumberLex(1.0));
-    expr.enqueue(Lexem::NumberLex(1.0));
+    let mut expr: Expression = Queue::new();
+    expr.enqueue((Lexem::NumberLex(Number::Float(1.0)), Span::default()));
+    expr.enqueue((Lexem::NumberLex(Number::Float(1.0)), Span::default()));
 
-    let op : Token = (TokenType::BinaryOperator, "+".to_string());
-    expr.enqueue(Lexem::OperatorLex(Operator::get_operator(&op)));
+    let op : Token = (TokenType::BinaryOperator, "+".to_string(), Span::default());
+    expr.enqueue((Lexem::OperatorLex(Operator::get_operator(&op)), Span::default()));
 
     let clc: Calculator = Calculator::new();
-    assert_eq!(clc.calculate(&mut expr), Ok(2.0));
+    assert_eq!(clc.calculate(&Expr::from_rpn(expr).unwrap()), Ok(Value::Number(Number::Float(2.0))));
 }
 
 #[test]
 fn test_calculate_two_plus_two_mult_two() {
     // проверка приоритетов операторов через решение 2+2*2 => 2 2 2 * +
-    let mut expr: Queue<Lexem> = Queue::new();
-    expr.enqueue(Lexem::NumberLex(2.0));
-    expr.enqueue(Lexem::NumberLex(2.0));
-    expr.enqueue(Lexem::NumberLex(2.0));
+    let mut expr: Expression = Queue::new();
+    expr.enqueue((Lexem::NumberLex(Number::Float(2.0)), Span::default()));
+    expr.enqueue((Lexem::NumberLex(Number::Float(2.0)), Span::default()));
+    expr.enqueue((Lexem::NumberLex(Number::Float(2.0)), Span::default()));
+
+    let op1 : Token = (TokenType::BinaryOperator, "*".to_string(), Span::default());
+    expr.enqueue((Lexem::OperatorLex(Operator::get_operator(&op1)), Span::default()));
+
+    let op2 : Token = (TokenType::BinaryOperator, "+".to_string(), Span::default());
+    expr.enqueue((Lexem::OperatorLex(Operator::get_operator(&op2)), Span::default()));
+
+    let clc: Calculator = Calculator::new();
+    assert_eq!(clc.calculate(&Expr::from_rpn(expr).unwrap()), Ok(Value::Number(Number::Float(6.0))));
+
+}
+
+#[test]
+fn test_calculate_operands_order() {
+    // 5-3 => 5 3 -
+    let mut expr: Expression = Queue::new();
+    expr.enqueue((Lexem::NumberLex(Number::Float(5.0)), Span::default()));
+    expr.enqueue((Lexem::NumberLex(Number::Float(3.0)), Span::default()));
+
+    let op : Token = (TokenType::BinaryOperator, "-".to_string(), Span::default());
+    expr.enqueue((Lexem::OperatorLex(Operator::get_operator(&op)), Span::default()));
+
+    let clc: Calculator = Calculator::new();
+    assert_eq!(clc.calculate(&Expr::from_rpn(expr).unwrap()), Ok(Value::Number(Number::Float(2.0))));
+}
+
+#[test]
+fn test_calculate_unknown_function() {
+    let mut expr: Expression = Queue::new();
+    expr.enqueue((Lexem::NumberLex(Number::Float(1.0)), Span::default()));
+
+    let func : Token = (TokenType::Function, "foo".to_string(), Span::default());
+    expr.enqueue((Lexem::CallLex(Operator::get_operator(&func), 1), Span::default()));
+
+    let clc: Calculator = Calculator::new();
+    assert!(clc.calculate(&Expr::from_rpn(expr).unwrap()).is_err());
+}
 
-    let op1 : Token = (TokenType::BinaryOperator, "*".to_string());
-    expr.enqueue(Lexem::OperatorLex(Operator::get_operator(&op1)));
+#[test]
+fn test_calculate_function_arity() {
+    // pow(2) => 2 pow[1]
+    let mut expr: Expression = Queue::new();
+    expr.enqueue((Lexem::NumberLex(Number::Float(2.0)), Span::default()));
 
-    let op2 : Token = (TokenType::BinaryOperator, "+".to_string());
-    expr.enqueue(Lexem::OperatorLex(Operator::get_operator(&op2)));
+    let func : Token = (TokenType::Function, "pow".to_string(), Span::default());
+    expr.enqueue((Lexem::CallLex(Operator::get_operator(&func), 1), Span::default()));
 
     let clc: Calculator = Calculator::new();
-    assert_eq!(clc.calculate(&mut expr), Ok(6.0));
+    assert!(clc.calculate(&Expr::from_rpn(expr).unwrap()).is_err());
+}
+
+#[test]
+fn test_calculate_variables() {
+    // x*2 => x 2 ×
+    let mut expr: Expression = Queue::new();
+    expr.enqueue((Lexem::VariableLex("x".to_string()), Span::default()));
+    expr.enqueue((Lexem::NumberLex(Number::Float(2.0)), Span::default()));
+
+    let op : Token = (TokenType::BinaryOperator, "*".to_string(), Span::default());
+    expr.enqueue((Lexem::OperatorLex(Operator::get_operator(&op)), Span::default()));
+
+    let mut clc: Calculator = Calculator::new();
+    clc.env.set("x", Number::Float(21.0));
+    assert_eq!(clc.calculate(&Expr::from_rpn(expr).unwrap()), Ok(Value::Number(Number::Float(42.0))));
+
+    let mut expr: Expression = Queue::new();
+    expr.enqueue((Lexem::VariableLex("y".to_string()), Span::default()));
+    assert!(clc.calculate(&Expr::from_rpn(expr).unwrap()).is_err());
+}
+
+#[test]
+fn test_calculate_traced() {
+    // 5-3 => 5 3 -
+    let mut expr: Expression = Queue::new();
+    expr.enqueue((Lexem::NumberLex(Number::Float(5.0)), Span::default()));
+    expr.enqueue((Lexem::NumberLex(Number::Float(3.0)), Span::default()));
+
+    let op : Token = (TokenType::BinaryOperator, "-".to_string(), Span::default());
+    expr.enqueue((Lexem::OperatorLex(Operator::get_operator(&op)), Span::default()));
+
+    let clc: Calculator = Calculator::new();
+    let mut trace = Trace::new();
+    assert_eq!(clc.calculate_traced(&Expr::from_rpn(expr).unwrap(), &mut trace), Ok(Value::Number(Number::Float(2.0))));
+
+    let stacks: Vec<(&str, &str)> = trace.evaluation.iter().map(|s| (s.1.as_str(), s.2.as_str())).collect();
+    assert_eq!(stacks, [("", "5"), ("5", "5 3"), ("5 3", "2")]);
+}
+
+#[test]
+fn test_calculate_conditional_is_lazy() {
+    let mut calc = crate::default_builder().build("").unwrap();
+    let mut result = |input: &str| calc.evaluate_line(input).map(|(_, v)| v.to_string());
+
+    assert_eq!(result("x = 0"), Ok("0".to_string()));
+    assert_eq!(result("x != 0 ? 1/x : -1"), Ok("-1".to_string()));
+    assert_eq!(result("if(x == 0, 1, 1/x)"), Ok("1".to_string()));
+    assert_eq!(result("x == 0 || 1/x > 1"), Ok("true".to_string()));
+    assert_eq!(result("x != 0 && 1/x > 1"), Ok("false".to_string()));
+
+    assert!(result("x == 0 ? 1/x : 1").is_err());
+    assert!(result("x ? 1 : 2").is_err());
+}
+
+#[test]
+fn test_user_functions() {
+    let mut builder = crate::default_builder();
+    let mut calc = builder.recursion_limit(50).build("").unwrap();
+
+    assert_eq!(calc.define_line("f(x, y) = x^2 + y"), Ok(Some("f(x, y) = x ^ 2 + y".to_string())));
+    assert_eq!(calc.define_line("fact(n) = n <= 1 ? 1 : n * fact(n - 1)").map(|d| d.is_some()), Ok(true));
+    assert_eq!(calc.define_line("x = 2"), Ok(None));
+    calc.evaluate_line("x = 10").unwrap();
+
+    let mut result = |input: &str| calc.evaluate_line(input).map(|(_, v)| v.to_string());
+    assert_eq!(result("f(3, 1) + x"), Ok("20".to_string()));
+    assert_eq!(result("fact(5)"), Ok("120".to_string()));
+    assert!(result("fact(60)").is_err());
+    assert!(result("f(1)").is_err());
+    assert!(result("q(1)").is_err());
+
+    // параметры не изменяют переменные окружения, новое определение заменяет прежнее
+    assert_eq!(calc.env.get("x"), Some(&Value::Number(Number::parse("10").unwrap())));
+    calc.define_line("f(x, y) = x - y").unwrap();
+    assert_eq!(calc.evaluate_line("f(3, 1)").map(|(_, v)| v.to_string()), Ok("2".to_string()));
+    assert_eq!(calc.definitions.list().count(), 2);
+
+    assert!(calc.define_line("sin(x) = x").is_err());
+    assert!(calc.define_line("h(x, x) = x").is_err());
+    assert!(calc.define_line("h(x, 1) = x").is_err());
+}
+
+#[test]
+fn test_deep_recursion() {
+    // рекурсия до большой допустимой глубины завершается ошибкой, а не переполнением стека
+    let mut builder = crate::default_builder();
+    let mut calc = builder.recursion_limit(1000).build("").unwrap();
+
+    calc.define_line("q(x) = q(x)").unwrap();
+    calc.define_line(&format!("p(x) = x + {}p(x)", "-".repeat(400))).unwrap();
+    for input in ["q(1)", "p(1)"] {
+        let error = calc.evaluate_line(input).err().unwrap();
+        assert!(error.to_string().contains("превышена допустимая глубина вызовов функций: 1000"));
+    }
+}
+
+#[test]
+fn test_constants_shadowing() {
+    let mut calc = crate::default_builder().build("").unwrap();
+
+    assert_eq!(calc.define_line("g(x) = x > 0 ? x + g(x - 1) : 0").map(|d| d.is_some()), Ok(true));
+    assert_eq!(calc.define_line("h(c) = 2*c"), Ok(Some("h(c) = 2 * c".to_string())));
+
+    let mut result = |input: &str| calc.evaluate_line(input).map(|(_, v)| v.to_string());
+    assert_eq!(result("g(3) + h(1)"), Ok("8".to_string()));
+    assert_eq!(result("2*g"), Ok("19.6133".to_string()));
+    assert_eq!(result("e = c / 299792458"), Ok("1".to_string()));
+    assert_eq!(result("e + 1"), Ok("2".to_string()));
+    assert_eq!(result("round(pi)"), Ok("3".to_string()));
+}
+
+#[test]
+fn test_imaginary_unit_name() {
+    let mut calc = crate::default_builder().build("").unwrap();
+    assert_eq!(calc.evaluate_line("i = 5").map(|(_, v)| v.to_string()), Ok("5".to_string()));
+    assert_eq!(calc.evaluate_line("2*i").map(|(_, v)| v.to_string()), Ok("10".to_string()));
+
+    let mut builder = crate::default_builder();
+    let mut calc = builder.backend(Rc::new(crate::ComplexBackend {})).build("").unwrap();
+    assert_eq!(calc.evaluate_line("i*i").map(|(_, v)| v.to_string()), Ok("-1".to_string()));
+    assert!(calc.evaluate_line("i = 5").is_err());
+}
+
+#[test]
+fn test_calculate_postfix_operators() {
+    let mut calc = crate::default_builder().build("").unwrap();
+    let mut result = |input: &str| calc.evaluate_line(input).map(|(_, v)| v.to_string());
+
+    assert_eq!(result("5! - 3!!"), Ok("117".to_string()));
+    assert_eq!(result("-3! + 2^3!"), Ok("58".to_string()));
+    assert_eq!(result("round(0.5! * 1000)"), Ok("886".to_string()));
+    assert_eq!(result("200 + 10%"), Ok("220".to_string()));
+    assert_eq!(result("200 - 10% * 2"), Ok("199.8".to_string()));
+    assert_eq!(result("50 * 10% + 7 % 3"), Ok("6".to_string()));
+    assert_eq!(result("7 % -3"), Ok("1".to_string()));
+    assert_eq!(result("7 % √4"), Ok("1".to_string()));
+    assert_eq!(result("7 % ~1 + 10% - 3"), Ok("-1.9".to_string()));
+
+    assert!(result("(-2)!").is_err());
+    assert!(result("2.5!!").is_err());
+    assert_eq!(result("!!true || 3!! == 3"), Ok("true".to_string()));
+
+    let infix = |input: &str| calc.parse_line(input).unwrap().to_string();
+    assert_eq!(infix("(3!)!"), "(3!)!");
+    assert_eq!(infix("(-3)! + -3!"), "(-3)! + -3!");
+    assert_eq!(infix("(200 + 10)%"), "(200 + 10)%");
+}
+
+#[test]
+fn test_derive_line() {
+    let calc = crate::default_builder().build("").unwrap();
+    let derive = |input: &str| calc.derive_line(input).map(|expr| expr.map(|expr| expr.to_string()));
+
+    assert_eq!(derive("d/dx(x^2*sin(x))"), Ok(Some("2 * x * sin(x) + x ^ 2 * cos(x)".to_string())));
+    assert_eq!(derive("d/dt(t^2 + x)"), Ok(Some("2 * t".to_string())));
+    assert_eq!(derive("d / 2"), Ok(None));
+    assert!(matches!(derive("d/dx(x) + 1"), Err(CalcError::Validation(..))));
+    assert!(matches!(derive("d/dx(x!)"), Err(CalcError::Differentiation(..))));
+}
+
+#[test]
+fn test_derive_line_user_names() {
+    // запись с переменной `d` или функцией `dx` сеанса вычисляется как обычное выражение
+    let mut calc = crate::default_builder().build("").unwrap();
+    calc.evaluate_line("d = 6").unwrap();
+    assert!(calc.derive_line("d/dx(x)").unwrap().is_none());
+    calc.define_line("dx(y) = y + 1").unwrap();
+
+    assert!(calc.derive_line("d/dx(2)").unwrap().is_none());
+    assert_eq!(calc.evaluate_line("d/dx(2)").map(|(_, v)| v.to_string()), Ok("2".to_string()));
 
+    let mut calc = crate::default_builder().build("").unwrap();
+    calc.define_line("dt(y) = y").unwrap();
+    assert!(calc.derive_line("d/dt(t)").unwrap().is_none());
 }
@@ -0,0 +1,259 @@
+//!
+//! Разбор аргументов командной строки
+//!
+use less_3_task::functions::AngleUnit;
+use less_3_task::writer::WordSize;
+use less_3_task::{DEFAULT_PRECISION, DEFAULT_RECURSION_LIMIT};
+
+///
+/// Формат вывода результатов
+///   - Text  - оформленный вывод для интерактивной работы
+///   - Plain - только результаты, по одному в строке
+///   - Json  - каждый результат или ошибка отдельным объектом JSON
+///
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Plain,
+    Json,
+}
+
+///
+/// Числовая система, в которой выполняются вычисления
+///   - Decimal  - точные десятичные числа с заданным количеством значащих цифр
+///   - Float    - числа с плавающей точкой двойной точности
+///   - Rational - точные несократимые дроби
+///   - Complex  - комплексные числа
+///   - Integer  - целые числа произвольного размера
+///
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NumericMode {
+    Decimal,
+    Float,
+    Rational,
+    Complex,
+    Integer,
+}
+
+///
+/// Настройки запуска программы, полученные из аргументов командной строки
+///
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub expressions: Vec<String>, // выражения, переданные ключом -e
+    pub file: Option<String>,     // файл с выражениями
+    pub output: OutputFormat,
+    pub precision: u64,
+    pub mode: NumericMode,
+    pub strict: bool,                // ошибка вместо перехода к плавающей точке в режиме rational
+    pub approximation: Option<u64>,  // количество цифр десятичного приближения дробей
+    pub polar: bool,                 // вывод комплексных чисел в полярной форме
+    pub programmer: bool,            // вывод целых результатов в разных системах счисления
+    pub word: WordSize,
+    pub unsigned: bool,
+    pub implicit: bool,              // неявное умножение: 2(3+4), 2pi
+    pub angle_unit: AngleUnit,
+    pub recursion_limit: usize,      // глубина вложенных вызовов пользовательских функций
+    pub trace: bool,
+    pub rpn_only: bool,
+    pub help: bool,
+    pub version: bool,
+}
+
+// Настройки по-умолчанию - интерактивный режим
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            expressions: Vec::new(),
+            file: None,
+            output: OutputFormat::Text,
+            precision: DEFAULT_PRECISION,
+            mode: NumericMode::Decimal,
+            strict: false,
+            approximation: None,
+            polar: false,
+            programmer: false,
+            word: WordSize::Bits64,
+            unsigned: false,
+            implicit: false,
+            angle_unit: AngleUnit::Radians,
+            recursion_limit: DEFAULT_RECURSION_LIMIT,
+            trace: false,
+            rpn_only: false,
+            help: false,
+            version: false,
+        }
+    }
+}
+
+// Наибольшая глубина вызовов пользовательских функций, которую можно задать ключом --depth
+// Кадры вызовов хранятся в куче, а не в стеке потока, ограничение лишь быстрее прерывает бесконечную рекурсию
+const MAX_RECURSION_LIMIT: usize = 1000;
+
+///
+/// Справка по аргументам командной строки
+///
+pub const USAGE: &str = r#"Использование: less_3_task [КЛЮЧИ] [ФАЙЛ]
+
+Без аргументов запускается интерактивный режим. Если передан ФАЙЛ, либо выражения поступают
+  на стандартный ввод через канал, то выражения вычисляются построчно в пакетном режиме.
+
+Ключи:
+  -e, --expr ВЫРАЖЕНИЕ      вычислить выражение (ключ можно указать несколько раз)
+  -o, --output ФОРМАТ       формат вывода: text (по-умолчанию), plain, json
+  -p, --precision ЧИСЛО     количество значащих цифр результата (по-умолчанию 28)
+  -m, --mode СИСТЕМА        числовая система: decimal (по-умолчанию), float, rational, complex,
+                            integer
+      --strict              в системе rational завершать ошибкой операции с нерациональным
+                            результатом (sqrt(2), sin) вместо перехода к плавающей точке
+      --approx ЧИСЛО        выводить после дроби её десятичное приближение с заданным
+                            количеством значащих цифр
+      --polar               выводить комплексные числа в полярной форме
+  -P, --programmer          режим программиста: целые результаты выводятся в десятичной,
+                            шестнадцатеричной, восьмеричной и двоичной системах
+      --word БИТ            размер машинного слова в режиме программиста: 8, 16, 32, 64
+                            (по-умолчанию 64)
+      --unsigned            беззнаковая интерпретация машинного слова в режиме программиста
+  -i, --implicit            разрешить неявное умножение: 2(3+4), (1+2)(3+4), 2pi, 3sin(x)
+  -a, --angle ЕДИНИЦЫ       единицы измерения углов: rad (по-умолчанию), deg, grad
+      --depth ЧИСЛО         допустимая глубина вложенных вызовов пользовательских функций
+                            от 1 до 1000 (по-умолчанию 100)
+  -t, --trace               пошаговый вывод преобразования и вычисления
+  -r, --rpn                 только преобразовать выражение в обратную польскую нотацию
+  -h, --help                вывести эту справку
+  -V, --version             вывести версию программы"#;
+
+///
+/// Разбор аргументов командной строки (без имени программы)
+/// В случае ошибки возвращается её описание
+///
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        // значение ключа - следующий аргумент
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format!("для ключа {} не указано значение", arg))
+        };
+
+        match arg.as_str() {
+            "-e" | "--expr" => options.expressions.push(value()?.clone()),
+            "-o" | "--output" => {
+                options.output = match value()?.as_str() {
+                    "text" => OutputFormat::Text,
+                    "plain" => OutputFormat::Plain,
+                    "json" => OutputFormat::Json,
+                    other => return Err(format!("неизвестный формат вывода '{}'", other)),
+                }
+            }
+            "-p" | "--precision" => {
+                let v = value()?;
+                options.precision = match v.parse::<u64>() {
+                    Ok(precision) if precision > 0 => precision,
+                    _ => return Err(format!("некорректное количество значащих цифр '{}'", v)),
+                }
+            }
+            "-m" | "--mode" => {
+                options.mode = match value()?.as_str() {
+                    "decimal" => NumericMode::Decimal,
+                    "float" => NumericMode::Float,
+                    "rational" => NumericMode::Rational,
+                    "complex" => NumericMode::Complex,
+                    "integer" => NumericMode::Integer,
+                    other => return Err(format!("неизвестная числовая система '{}'", other)),
+                }
+            }
+            "--strict" => options.strict = true,
+            "--polar" => options.polar = true,
+            "-P" | "--programmer" => options.programmer = true,
+            "--word" => {
+                let v = value()?;
+                options.word = WordSize::parse(v).ok_or_else(|| format!("недопустимый размер слова '{}'", v))?;
+            }
+            "--unsigned" => options.unsigned = true,
+            "-i" | "--implicit" => options.implicit = true,
+            "--approx" => {
+                let v = value()?;
+                options.approximation = match v.parse::<u64>() {
+                    Ok(digits) if digits > 0 => Some(digits),
+                    _ => return Err(format!("некорректное количество цифр приближения '{}'", v)),
+                }
+            }
+            "-a" | "--angle" => {
+                let v = value()?;
+                options.angle_unit = AngleUnit::parse(v)
+                    .ok_or_else(|| format!("неизвестные единицы измерения углов '{}'", v))?;
+            }
+            "--depth" => {
+                let v = value()?;
+                options.recursion_limit = match v.parse::<usize>() {
+                    Ok(depth) if depth > 0 && depth <= MAX_RECURSION_LIMIT => depth,
+                    _ => return Err(format!("некорректная глубина вызовов '{}'", v)),
+                }
+            }
+            "-t" | "--trace" => options.trace = true,
+            "-r" | "--rpn" => options.rpn_only = true,
+            "-h" | "--help" => options.help = true,
+            "-V" | "--version" => options.version = true,
+            key if key.starts_with('-') && key.len() > 1 => {
+                return Err(format!("неизвестный ключ '{}'", key))
+            }
+            path => {
+                if options.file.is_some() {
+                    return Err(format!("лишний аргумент '{}'", path));
+                }
+                options.file = Some(path.to_string());
+            }
+        }
+    }
+
+    if options.file.is_some() && !options.expressions.is_empty() {
+        return Err("ключ -e нельзя использовать вместе с файлом".to_string());
+    }
+
+    Ok(options)
+}
+
+// Базовые тесты
+#[cfg(test)]
+fn args(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_parse_args() {
+    let options = parse_args(&args(&["-e", "2+2", "--output", "json", "-p", "10", "-a", "deg", "-t", "-m", "rational", "--approx", "5", "-i"])).unwrap();
+
+    assert_eq!(options.expressions, vec!["2+2".to_string()]);
+    assert_eq!(options.output, OutputFormat::Json);
+    assert_eq!(options.precision, 10);
+    assert_eq!(options.angle_unit, AngleUnit::Degrees);
+    assert_eq!((options.mode, options.approximation), (NumericMode::Rational, Some(5)));
+    assert!(options.trace && options.implicit);
+    assert!(!options.rpn_only);
+
+    assert_eq!(parse_args(&args(&["file.txt"])).unwrap().file, Some("file.txt".to_string()));
+    assert_eq!(parse_args(&[]).unwrap(), Options::default());
+}
+
+#[test]
+fn test_parse_args_errors() {
+    assert!(parse_args(&args(&["-e"])).is_err());
+    assert!(parse_args(&args(&["--output", "xml"])).is_err());
+    assert!(parse_args(&args(&["-p", "0"])).is_err());
+    assert!(parse_args(&args(&["--mode", "octonion"])).is_err());
+    assert!(parse_args(&args(&["--word", "12"])).is_err());
+    assert!(parse_args(&args(&["--depth", "-1"])).is_err());
+    assert!(parse_args(&args(&["--depth", "100000"])).is_err());
+    assert!(parse_args(&args(&["--unknown"])).is_err());
+    assert!(parse_args(&args(&["-e", "1", "file.txt"])).is_err());
+}
+
+#[test]
+fn test_max_depth_recursion() {
+    let options = parse_args(&args(&["--depth", &MAX_RECURSION_LIMIT.to_string()])).unwrap();
+    assert_eq!(options.recursion_limit, MAX_RECURSION_LIMIT);
+    assert!(parse_args(&args(&["--depth", &(MAX_RECURSION_LIMIT + 1).to_string()])).is_err());
+}
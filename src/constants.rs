@@ -0,0 +1,23 @@
+//!
+//! Встроенные математические и физические константы
+//!
+
+///
+/// Таблица встроенных констант: (имя константы, значение)
+/// Математические константы записаны с запасом значащих цифр для точных числовых систем,
+///   физические - в единицах СИ
+/// Для добавления новой константы достаточно дополнить таблицу
+///
+pub fn builtin_constants() -> Vec<(&'static str, &'static str)> {
+    vec![
+        ("pi", "3.14159265358979323846264338327950288419716939937510"),
+        ("e", "2.71828182845904523536028747135266249775724709369995"),
+        ("tau", "6.28318530717958647692528676655900576839433879875021"),
+        ("phi", "1.61803398874989484820458683436563811772030917980576"),
+        ("c", "299792458"),         // скорость света в вакууме, м/с
+        ("g", "9.80665"),           // стандартное ускорение свободного падения, м/с²
+        ("h", "6.62607015e-34"),    // постоянная Планка, Дж·с
+        ("k_B", "1.380649e-23"),    // постоянная Больцмана, Дж/К
+        ("N_A", "6.02214076e23"),   // число Авогадро, 1/моль
+    ]
+}
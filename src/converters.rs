@@ -1,17 +1,40 @@
+use std::rc::Rc;
+
+use crate::ast::Expr;
+use crate::error::CalcError;
 use crate::operator::{Expression, Lexem, Operator};
 use crate::queue::Queue;
+use crate::registry::{OperatorRegistry, CONDITIONAL_FUNCTION};
 use crate::stack::Stack;
-use crate::token::{Token, TokenList, TokenType};
+use crate::token::{Span, Token, TokenList, TokenType};
+use crate::trace::Trace;
 
 
 ///
 /// Типаж для определения преобразователя
 /// Принимает на вход список токенов
-/// В качестве результата выдает либо очередь чисел и операторов для вычисления,
-///  либо строку с описанием возникшей ошибки
+/// В качестве результата выдает либо дерево выражения для вычисления,
+///  либо ошибку с положением ошибочного токена
 ///
 pub trait Converter {
-    fn convert(&self, input: TokenList) -> Result<Expression, &str>;
+    fn convert(&self, input: TokenList) -> Result<Expr, CalcError>;
+
+    ///
+    /// Преобразование с записью шагов в журнал трассировки
+    /// Преобразователи, не поддерживающие трассировку, выполняют обычное преобразование
+    ///
+    fn convert_traced(&self, input: TokenList, _trace: &mut Trace) -> Result<Expr, CalcError> {
+        self.convert(input)
+    }
+
+    ///
+    /// Создание аналогичного преобразователя, использующего заданный реестр операторов
+    /// Используется строителем калькулятора при регистрации пользовательских операторов
+    /// Преобразователи, не зависящие от набора операторов, возвращают None
+    ///
+    fn with_operators(&self, _registry: &Rc<OperatorRegistry>) -> Option<Rc<dyn Converter>> {
+        None
+    }
 }
 
 ///
@@ -22,36 +45,98 @@ pub struct EmptyConverter {}
 
 // Пустая реализация для пустого преобразователя
 impl Converter for EmptyConverter {
-    fn convert(&self, _: TokenList) -> Result<Expression, &str> {
-        Err("Empty")
+    fn convert(&self, _: TokenList) -> Result<Expr, CalcError> {
+        Err(CalcError::Conversion("Empty".to_string(), Span::default()))
     }
 }
 
 ///
 /// Объект для преобразования входной последовательности токенов в очередь
 ///   соответствующей обратной польской нотации
+/// Приоритет и ассоциативность операторов берутся из реестра операторов
 ///
-pub struct InfixToRPN;
+pub struct InfixToRPN {
+    registry: Rc<OperatorRegistry>,
+}
 
-// Реализация типажа преобразования для объекта InfixToRPN
-impl Converter for InfixToRPN {
+// Имплементация типажа для возможности создания объекта со значениями по-умолчанию
+impl Default for InfixToRPN {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Реализация методов объекта InfixToRPN
+impl InfixToRPN {
+    ///
+    /// Создание преобразователя со стандартными операторами
+    ///
+    pub fn new() -> Self {
+        InfixToRPN::with_registry(Rc::new(OperatorRegistry::standard()))
+    }
+
+    ///
+    /// Создание преобразователя с заданным реестром операторов
     ///
-    /// Фукнция преобразования из инфиксной записи в префиксную
+    pub fn with_registry(registry: Rc<OperatorRegistry>) -> Self {
+        InfixToRPN { registry }
+    }
+
+    ///
+    /// Фукнция преобразования из инфиксной записи в обратную польскую
     /// Используется алгоритм сортировочной станции Э. Дейкстра
     ///
-    fn convert(&self, input: TokenList) -> Result<Expression, &str> {
+    pub fn to_rpn(&self, input: TokenList) -> Result<Expression, CalcError> {
+        self.shunting_yard(input, None)
+    }
+
+    ///
+    /// Преобразование из инфиксной записи в обратную польскую с записью каждого шага в журнал трассировки
+    ///
+    pub fn to_rpn_traced(&self, input: TokenList, trace: &mut Trace) -> Result<Expression, CalcError> {
+        self.shunting_yard(input, Some(trace))
+    }
+
+    // Реализация алгоритма сортировочной станции
+    // Если передан журнал трассировки, то в него записывается каждое действие алгоритма
+    //   вместе с состоянием стека операторов и выходной очереди после его выполнения
+    fn shunting_yard(&self, input: TokenList, mut trace: Option<&mut Trace>) -> Result<Expression, CalcError> {
         let mut stack: Stack<Token> = Stack::new();
         let mut output: Expression = Queue::new();
+        // Для каждой открытой скобки хранится количество аргументов вызова функции,
+        //   либо None, если скобка не открывает вызов функции
+        let mut calls: Stack<Option<usize>> = Stack::new();
+        // Тип предыдущего токена и конец его положения во входной строке
+        let mut prev: Option<TokenType> = None;
+        let mut last_end: usize = 0;
 
         for tok in input {
+            let (kind, end) = (tok.0, tok.2.end);
             match tok.0 {
-                TokenType::NumberInt | TokenType::NumberFloat => {
-                    // Если токен — число, то добавить его в очередь вывода
-                    output.enqueue(Lexem::new(&tok));
+                TokenType::NumberInt
+                | TokenType::NumberFloat
+                | TokenType::NumberImaginary
+                | TokenType::NumberRadix(_)
+                | TokenType::Boolean
+                | TokenType::Identifier
+                | TokenType::Constant => {
+                    // Если предыдущий токен — число, логический литерал, переменная, константа, закрывающая скобка
+                    //   или постфиксный оператор, то между операндами пропущен оператор или разделитель аргументов
+                    if InfixToRPN::after_operand(prev) {
+                        return Err(CalcError::Conversion(
+                            "в выражении пропущен оператор, либо разделитель аргументов функции (запятая)".to_string(),
+                            tok.2,
+                        ));
+                    }
+
+                    // Если токен — число, логический литерал, переменная или константа, то добавить его в очередь вывода
+                    output.enqueue((Lexem::new(&tok, &self.registry)?, tok.2));
+                    InfixToRPN::record(&mut trace, &tok.1, "операнд в выходную очередь", &stack, &output);
                 }
                 TokenType::Function => {
                     // Если токен — функция, то поместить его в стек
-                    stack.push(tok);
+                    stack.push(tok.clone());
+                    InfixToRPN::record(&mut trace, &tok.1, "функцию в стек", &stack, &output);
                 }
                 TokenType::ArgumentSeparator => {
                     // Если токен — разделитель аргументов функции (например запятая):
@@ -60,7 +145,10 @@ impl Converter for InfixToRPN {
                     while let Some(last) = stack.peek() {
                         if last.0 != TokenType::OpenedParenthesis {
                             let op = stack.pop().unwrap();
-                            output.enqueue(Lexem::new(&op));
+                            InfixToRPN::check_conditional(&op)?;
+                            output.enqueue((Lexem::new(&op, &self.registry)?, op.2));
+                            let action = format!("{} из стека в выходную очередь", op.1);
+                            InfixToRPN::record(&mut trace, &tok.1, &action, &stack, &output);
                         } else {
                             break;
                         }
@@ -70,33 +158,149 @@ impl Converter for InfixToRPN {
                     //   то в выражении пропущен разделитель аргументов функции (запятая),
                     //   либо пропущена открывающая скобка.
                     if stack.is_empty() {
-                        return Err("в выражении пропущен разделитель аргументов функции (запятая), либо пропущена открывающая скобка");
+                        return Err(CalcError::Conversion(
+                            "в выражении пропущен разделитель аргументов функции (запятая), либо пропущена открывающая скобка".to_string(),
+                            tok.2,
+                        ));
+                    }
+
+                    // Разделитель допустим только внутри вызова функции и увеличивает количество её аргументов
+                    match calls.pop() {
+                        Some(Some(count)) => {
+                            calls.push(Some(count + 1));
+                            InfixToRPN::record(&mut trace, &tok.1, "следующий аргумент функции", &stack, &output);
+                        }
+                        _ => {
+                            return Err(CalcError::Conversion(
+                                "разделитель аргументов функции (запятая) вне вызова функции".to_string(),
+                                tok.2,
+                            ))
+                        }
                     }
                 }
-                TokenType::UnaryOperator => {
-                    stack.push(tok);
+                TokenType::UnaryOperator | TokenType::PrefixOperator => {
+                    // Префиксный оператор после операнда (`2√4`) не становится бинарным, значит пропущен оператор
+                    if kind == TokenType::PrefixOperator && InfixToRPN::after_operand(prev) {
+                        return Err(CalcError::Conversion(
+                            format!("в выражении пропущен оператор перед '{}'", tok.1),
+                            Span::new(last_end, tok.2.start),
+                        ));
+                    }
+
+                    stack.push(tok.clone());
+                    InfixToRPN::record(&mut trace, &tok.1, "унарный оператор в стек", &stack, &output);
+                }
+                TokenType::PostfixOperator => {
+                    // Постфиксный оператор применяется к уже прочитанному операнду
+                    if !InfixToRPN::after_operand(prev) {
+                        return Err(CalcError::Conversion(
+                            format!("постфиксный оператор '{}' должен следовать за операндом", tok.1),
+                            tok.2,
+                        ));
+                    }
+
+                    // Переложить из стека в выходную очередь операторы, связывающие сильнее,
+                    //   затем сразу добавить постфиксный оператор в выходную очередь
+                    let op1 = Operator::from_registry(&self.registry, &tok);
+                    while let Some(last) = stack.peek() {
+                        if op1.yields_to(&Operator::from_registry(&self.registry, last)) {
+                            output.enqueue((Lexem::new(last, &self.registry)?, last.2));
+                            let action = format!("{} из стека в выходную очередь", last.1);
+                            let _ = stack.pop();
+                            InfixToRPN::record(&mut trace, &tok.1, &action, &stack, &output);
+                        } else {
+                            break;
+                        }
+                    }
+
+                    output.enqueue((Lexem::OperatorLex(op1), tok.2));
+                    InfixToRPN::record(&mut trace, &tok.1, "постфиксный оператор в выходную очередь", &stack, &output);
                 }
                 TokenType::BinaryOperator => {
                     // Если токен — оператор op1, то:
                     //     Пока присутствует на вершине стека токен оператор op2,
-                    //       чей приоритет выше или равен приоритету op1,
-                    //       и при равенстве приоритетов op1 является левоассоциативным:
+                    //       чей приоритет выше приоритета op1, либо приоритеты равны
+                    //       и op1 является левоассоциативным:
                     //         Переложить op2 из стека в выходную очередь;
+                    let op1 = Operator::from_registry(&self.registry, &tok);
                     while let Some(last) = stack.peek() {
-                        if Operator::get_operator(&tok) >= Operator::get_operator(&last) {
-                            output.enqueue(Lexem::new(&last));
+                        if op1.yields_to(&Operator::from_registry(&self.registry, last)) {
+                            output.enqueue((Lexem::new(last, &self.registry)?, last.2));
+                            let action = format!("{} из стека в выходную очередь", last.1);
                             let _ = stack.pop();
+                            InfixToRPN::record(&mut trace, &tok.1, &action, &stack, &output);
                         } else {
                             break;
                         }
                     }
 
                     // Положить op1 в стек.
-                    stack.push(tok);
+                    stack.push(tok.clone());
+                    InfixToRPN::record(&mut trace, &tok.1, "оператор в стек", &stack, &output);
+                }
+                TokenType::QuestionMark => {
+                    // Тернарный оператор связывает слабее всех операторов: переложить из стека в выходную очередь
+                    //   все операторы до открывающей скобки, либо другого тернарного оператора
+                    //   (правая ассоциативность), и положить условие в стек
+                    while let Some(last) = stack.peek() {
+                        if Operator::from_registry(&self.registry, last).precedence().is_some() {
+                            output.enqueue((Lexem::new(last, &self.registry)?, last.2));
+                            let action = format!("{} из стека в выходную очередь", last.1);
+                            let _ = stack.pop();
+                            InfixToRPN::record(&mut trace, &tok.1, &action, &stack, &output);
+                        } else {
+                            break;
+                        }
+                    }
+
+                    stack.push(tok.clone());
+                    InfixToRPN::record(&mut trace, &tok.1, "условие в стек", &stack, &output);
+                }
+                TokenType::Colon => {
+                    // Переложить операторы ветви "истина" из стека в выходную очередь до условия тернарного оператора
+                    while let Some(last) = stack.peek() {
+                        if last.0 == TokenType::QuestionMark || last.0 == TokenType::OpenedParenthesis {
+                            break;
+                        }
+                        let op = stack.pop().unwrap();
+                        output.enqueue((Lexem::new(&op, &self.registry)?, op.2));
+                        let action = format!("{} из стека в выходную очередь", op.1);
+                        InfixToRPN::record(&mut trace, &tok.1, &action, &stack, &output);
+                    }
+
+                    // Условие заменяется в стеке тернарным оператором, ожидающим ветвь "ложь"
+                    match stack.pop() {
+                        Some(question) if question.0 == TokenType::QuestionMark => {
+                            stack.push((TokenType::Colon, "?:".to_string(), question.2));
+                            InfixToRPN::record(&mut trace, &tok.1, "тернарный оператор в стек", &stack, &output);
+                        }
+                        _ => {
+                            return Err(CalcError::Conversion(
+                                "в условном выражении пропущено условие '?'".to_string(),
+                                tok.2,
+                            ))
+                        }
+                    }
                 }
                 TokenType::OpenedParenthesis => {
+                    // Скобка сразу после операнда не может начинать ни вызов функции, ни новый операнд
+                    if InfixToRPN::after_operand(prev) {
+                        return Err(CalcError::Conversion(
+                            "в выражении пропущен оператор перед открывающей скобкой".to_string(),
+                            tok.2,
+                        ));
+                    }
+
+                    // Если скобка следует за функцией, то начать подсчет аргументов её вызова
+                    if prev == Some(TokenType::Function) {
+                        calls.push(Some(1));
+                    } else {
+                        calls.push(None);
+                    }
+
                     // Если токен — открывающая скобка, то положить его в стек
-                    stack.push(tok);
+                    stack.push(tok.clone());
+                    InfixToRPN::record(&mut trace, &tok.1, "скобку в стек", &stack, &output);
                 }
                 TokenType::ClosedParenthesis => {
                     // Если токен — закрывающая скобка:
@@ -106,24 +310,68 @@ impl Converter for InfixToRPN {
                         && stack.peek().unwrap().0 != TokenType::OpenedParenthesis
                     {
                         let op = stack.pop().unwrap();
-                        output.enqueue(Lexem::new(&op));
+                        InfixToRPN::check_conditional(&op)?;
+                        output.enqueue((Lexem::new(&op, &self.registry)?, op.2));
+                        let action = format!("{} из стека в выходную очередь", op.1);
+                        InfixToRPN::record(&mut trace, &tok.1, &action, &stack, &output);
                     }
 
                     // Если стек закончился до того, как был встречен токен открывающая скобка, то в выражении пропущена скобка.
                     if stack.is_empty() {
-                        return Err("в выражении пропущена открывающая скобка");
+                        return Err(CalcError::Conversion(
+                            "в выражении пропущена открывающая скобка".to_string(),
+                            tok.2,
+                        ));
                     } else {
                         // Выкинуть открывающую скобку из стека, но не добавлять в очередь вывода.
-                        let _ = stack.pop();
-                        // Если токен на вершине стека — функция, переложить её в выходную очередь.
-                        if !stack.is_empty() && stack.peek().unwrap().0 == TokenType::Function {
+                        let opened = stack.pop().unwrap();
+                        InfixToRPN::record(&mut trace, &tok.1, "убрать открывающую скобку из стека", &stack, &output);
+                        let mut count = calls.pop().unwrap_or(None);
+
+                        // Пустые скобки допустимы только при вызове функции без аргументов
+                        if prev == Some(TokenType::OpenedParenthesis) {
+                            if count.is_none() {
+                                return Err(CalcError::Conversion(
+                                    "в выражении пустые скобки".to_string(),
+                                    opened.2.join(tok.2),
+                                ));
+                            }
+                            count = Some(0);
+                        }
+
+                        // Если токен на вершине стека — функция, переложить её в выходную очередь
+                        //   вместе с количеством аргументов вызова.
+                        if let Some(count) = count {
                             let op = stack.pop().unwrap();
-                            output.enqueue(Lexem::new(&op));
+                            // Функция условного выражения вычисляет только выбранную ветвь, как и тернарный оператор
+                            if op.1 == CONDITIONAL_FUNCTION {
+                                if count != 3 {
+                                    return Err(CalcError::Conversion(
+                                        format!("функция {} ожидает аргументов: 3, передано: {}", op.1, count),
+                                        op.2,
+                                    ));
+                                }
+                                output.enqueue((Lexem::CondLex, op.2));
+                            } else {
+                                output.enqueue((Lexem::CallLex(Operator::from_registry(&self.registry, &op), count), op.2));
+                            }
+                            let action = format!("вызов {} с аргументами: {} в выходную очередь", op.1, count);
+                            InfixToRPN::record(&mut trace, &tok.1, &action, &stack, &output);
                         }
                     }
                 }
+                TokenType::Assignment => {
+                    // Присваивание обрабатывается калькулятором до преобразования выражения
+                    return Err(CalcError::Conversion(
+                        "присваивание допустимо только в начале выражения".to_string(),
+                        tok.2,
+                    ));
+                }
                 TokenType::Whitespaces => println!("А вот такого быть не должно"),
             }
+
+            prev = Some(kind);
+            last_end = end;
         }
 
         // Если больше не осталось токенов на входе:
@@ -131,72 +379,361 @@ impl Converter for InfixToRPN {
         while let Some(last) = stack.peek() {
             // Если токен опратор на вершине стека — открывающая скобка, то в выражении пропущена скобка.
             if last.0 == TokenType::OpenedParenthesis {
-                return Err("в выражении пропущена скобка");
+                return Err(CalcError::Conversion(
+                    "в выражении пропущена закрывающая скобка".to_string(),
+                    last.2,
+                ));
             }
 
             // Переложить оператор из стека в выходную очередь.
             let op = stack.pop().unwrap();
-            output.enqueue(Lexem::new(&op));
+            InfixToRPN::check_conditional(&op)?;
+            output.enqueue((Lexem::new(&op, &self.registry)?, op.2));
+            let action = format!("{} из стека в выходную очередь", op.1);
+            InfixToRPN::record(&mut trace, "", &action, &stack, &output);
         }
 
         Ok(output)
     }
+
+    // Заканчивается ли операнд токеном данного типа: число, логический литерал, переменная, константа,
+    //   закрывающая скобка или постфиксный оператор
+    fn after_operand(prev: Option<TokenType>) -> bool {
+        match prev {
+            Some(kind) => {
+                kind.is_number()
+                    || kind == TokenType::Boolean
+                    || kind == TokenType::Identifier
+                    || kind == TokenType::Constant
+                    || kind == TokenType::ClosedParenthesis
+                    || kind == TokenType::PostfixOperator
+            }
+            None => false,
+        }
+    }
+
+    // Условие тернарного оператора, извлекаемое из стека без разделителя ветвей ':', является ошибкой
+    fn check_conditional(op: &Token) -> Result<(), CalcError> {
+        if op.0 == TokenType::QuestionMark {
+            return Err(CalcError::Conversion(
+                "в условном выражении пропущен разделитель ветвей ':'".to_string(),
+                op.2,
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Запись шага алгоритма в журнал трассировки, если он передан
+    fn record(trace: &mut Option<&mut Trace>, token: &str, action: &str, stack: &Stack<Token>, output: &Expression) {
+        if let Some(trace) = trace {
+            let stack: Vec<&str> = stack.stack.iter().map(|tok| tok.1.as_str()).collect();
+            let output: Vec<String> = output.queue.iter().map(|(lexem, _)| lexem.to_string()).collect();
+            trace.conversion_step(token, action.to_string(), stack.join(" "), output.join(" "));
+        }
+    }
+}
+
+
+///
+/// Объект для преобразования входной последовательности токенов в дерево выражения
+/// Выражение преобразуется в обратную польскую нотацию, по которой затем строится дерево
+///
+pub struct InfixToAst {
+    rpn: InfixToRPN,
+}
+
+// Имплементация типажа для возможности создания объекта со значениями по-умолчанию
+impl Default for InfixToAst {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Реализация методов объекта InfixToAst
+impl InfixToAst {
+    pub fn new() -> Self {
+        InfixToAst { rpn: InfixToRPN::new() }
+    }
+
+    ///
+    /// Создание преобразователя с заданным реестром операторов
+    ///
+    pub fn with_registry(registry: Rc<OperatorRegistry>) -> Self {
+        InfixToAst {
+            rpn: InfixToRPN::with_registry(registry),
+        }
+    }
+}
+
+// Реализация типажа преобразования для объекта InfixToAst
+impl Converter for InfixToAst {
+    fn convert(&self, input: TokenList) -> Result<Expr, CalcError> {
+        Expr::from_rpn(self.rpn.to_rpn(input)?)
+    }
+
+    fn convert_traced(&self, input: TokenList, trace: &mut Trace) -> Result<Expr, CalcError> {
+        Expr::from_rpn(self.rpn.to_rpn_traced(input, trace)?)
+    }
+
+    fn with_operators(&self, registry: &Rc<OperatorRegistry>) -> Option<Rc<dyn Converter>> {
+        Some(Rc::new(InfixToAst::with_registry(registry.clone())))
+    }
 }
 
 
 // Базовые тесты
 #[cfg(test)]
+use crate::token::token_list;
+
 #[test]
 fn test_convert() {
-    let test_tokens: TokenList = vec![
-        (TokenType::OpenedParenthesis, "(".to_string()),
-        (TokenType::NumberInt, "1".to_string()),
-        (TokenType::UnaryOperator, "+".to_string()),
-        (TokenType::UnaryOperator, "-".to_string()),
-        (TokenType::NumberFloat, "1.1".to_string()),
-        (TokenType::ClosedParenthesis, ")".to_string()),
-        (TokenType::BinaryOperator, "*".to_string()),
-        (TokenType::NumberInt, "2".to_string()),
-        (TokenType::BinaryOperator, "/".to_string()),
-        (TokenType::NumberInt, "3".to_string()),
-        (TokenType::BinaryOperator, ">>".to_string()),
-        (TokenType::NumberInt, "4".to_string()),
-        (TokenType::BinaryOperator, "<<".to_string()),
-        (TokenType::NumberInt, "5".to_string()),
-    ];
+    let test_tokens: TokenList = token_list(&[
+        (TokenType::OpenedParenthesis, "("),
+        (TokenType::NumberInt, "1"),
+        (TokenType::UnaryOperator, "+"),
+        (TokenType::UnaryOperator, "-"),
+        (TokenType::NumberFloat, "1.1"),
+        (TokenType::ClosedParenthesis, ")"),
+        (TokenType::BinaryOperator, "*"),
+        (TokenType::NumberInt, "2"),
+        (TokenType::BinaryOperator, "/"),
+        (TokenType::NumberInt, "3"),
+        (TokenType::BinaryOperator, ">>"),
+        (TokenType::NumberInt, "4"),
+        (TokenType::BinaryOperator, "<<"),
+        (TokenType::NumberInt, "5"),
+    ]);
 
-    let test_converter = InfixToRPN {};
+    let test_converter = InfixToRPN::new();
 
-    assert!(test_converter.convert(test_tokens).is_ok());
+    assert!(test_converter.to_rpn(test_tokens).is_ok());
 }
 
 #[test]
-#[ignore]
 fn test_convert_miss_separator() {
-    let test_tokens: TokenList = vec![
-        (TokenType::Function, "fn".to_string()),
-        (TokenType::OpenedParenthesis, "(".to_string()),
-        (TokenType::NumberInt, "1".to_string()),
-        (TokenType::ArgumentSeparator, ",".to_string()),
-        (TokenType::NumberInt, "1".to_string()),
-        (TokenType::NumberInt, "1".to_string()),
-        (TokenType::ClosedParenthesis, ")".to_string()),
-    ];
+    let test_tokens: TokenList = token_list(&[
+        (TokenType::Function, "fn"),
+        (TokenType::OpenedParenthesis, "("),
+        (TokenType::NumberInt, "1"),
+        (TokenType::ArgumentSeparator, ","),
+        (TokenType::NumberInt, "1"),
+        (TokenType::NumberInt, "1"),
+        (TokenType::ClosedParenthesis, ")"),
+    ]);
 
-    let test_converter = InfixToRPN {};
+    let test_converter = InfixToRPN::new();
 
-    assert!(test_converter.convert(test_tokens).is_err());
+    assert!(test_converter.to_rpn(test_tokens).is_err());
 }
 
 #[test]
 fn test_convert_miss_brace() {
+    let test_tokens: TokenList = token_list(&[
+        (TokenType::OpenedParenthesis, "("),
+        (TokenType::ClosedParenthesis, ")"),
+        (TokenType::ClosedParenthesis, ")"),
+    ]);
+
+    let test_converter = InfixToRPN::new();
+
+    assert!(test_converter.to_rpn(test_tokens).is_err());
+}
+
+#[test]
+fn test_convert_function_arguments_count() {
+    // max(1, 2, 3) => 1 2 3 max[3]
+    let test_tokens: TokenList = token_list(&[
+        (TokenType::Function, "max"),
+        (TokenType::OpenedParenthesis, "("),
+        (TokenType::NumberInt, "1"),
+        (TokenType::ArgumentSeparator, ","),
+        (TokenType::NumberInt, "2"),
+        (TokenType::ArgumentSeparator, ","),
+        (TokenType::NumberInt, "3"),
+        (TokenType::ClosedParenthesis, ")"),
+    ]);
+
+    let test_converter = InfixToRPN::new();
+    let mut expr = test_converter.to_rpn(test_tokens).unwrap();
+
+    let mut last = None;
+    while let Some((lexem, _)) = expr.dequeue() {
+        last = Some(lexem);
+    }
+    assert!(matches!(last, Some(Lexem::CallLex(_, 3))));
+}
+
+#[test]
+fn test_convert_separator_outside_call() {
+    let test_tokens: TokenList = token_list(&[
+        (TokenType::OpenedParenthesis, "("),
+        (TokenType::NumberInt, "1"),
+        (TokenType::ArgumentSeparator, ","),
+        (TokenType::NumberInt, "2"),
+        (TokenType::ClosedParenthesis, ")"),
+    ]);
+
+    let test_converter = InfixToRPN::new();
+
+    assert!(test_converter.to_rpn(test_tokens).is_err());
+}
+
+#[test]
+fn test_convert_error_span() {
+    // (1+2 => ошибка указывает на незакрытую скобку
+    let test_tokens: TokenList = token_list(&[
+        (TokenType::NumberInt, "1"),
+        (TokenType::BinaryOperator, "*"),
+        (TokenType::OpenedParenthesis, "("),
+        (TokenType::NumberInt, "1"),
+        (TokenType::BinaryOperator, "+"),
+        (TokenType::NumberInt, "2"),
+    ]);
+
+    let test_converter = InfixToRPN::new();
+    let error = test_converter.to_rpn(test_tokens).err().unwrap();
+
+    assert_eq!(error.span(), Some(Span::new(2, 3)));
+}
+
+#[test]
+fn test_convert_operand_before_parenthesis() {
+    // 1+pi(2) => ошибка указывает на скобку после константы
+    let test_tokens: TokenList = token_list(&[
+        (TokenType::NumberInt, "1"),
+        (TokenType::BinaryOperator, "+"),
+        (TokenType::Constant, "pi"),
+        (TokenType::OpenedParenthesis, "("),
+        (TokenType::NumberInt, "2"),
+        (TokenType::ClosedParenthesis, ")"),
+    ]);
+
+    let test_converter = InfixToRPN::new();
+    let error = test_converter.to_rpn(test_tokens).err().unwrap();
+
+    assert_eq!(error.span(), Some(Span::new(4, 5)));
+}
+
+#[test]
+fn test_convert_operand_before_prefix_operator() {
+    // 2 √4 без неявного умножения => ошибка указывает на промежуток между операндами
     let test_tokens: TokenList = vec![
-        (TokenType::OpenedParenthesis, "(".to_string()),
-        (TokenType::ClosedParenthesis, ")".to_string()),
-        (TokenType::ClosedParenthesis, ")".to_string()),
+        (TokenType::NumberInt, "2".to_string(), Span::new(0, 1)),
+        (TokenType::PrefixOperator, "√".to_string(), Span::new(2, 5)),
+        (TokenType::NumberInt, "4".to_string(), Span::new(5, 6)),
     ];
 
-    let test_converter = InfixToRPN {};
+    let test_converter = InfixToRPN::new();
+    let error = test_converter.to_rpn(test_tokens).err().unwrap();
+
+    assert_eq!(error.to_string(), "Ошибка преобразования: в выражении пропущен оператор перед '√'");
+    assert_eq!(error.span(), Some(Span::new(1, 2)));
+}
+
+#[test]
+fn test_convert_traced() {
+    // 1+2*3 => 1 2 3 × +
+    let test_tokens: TokenList = token_list(&[
+        (TokenType::NumberInt, "1"),
+        (TokenType::BinaryOperator, "+"),
+        (TokenType::NumberInt, "2"),
+        (TokenType::BinaryOperator, "*"),
+        (TokenType::NumberInt, "3"),
+    ]);
+
+    let mut trace = Trace::new();
+    let test_converter = InfixToRPN::new();
+    assert!(test_converter.to_rpn_traced(test_tokens, &mut trace).is_ok());
+
+    let actions: Vec<&str> = trace.conversion.iter().map(|step| step.1.as_str()).collect();
+    assert_eq!(
+        actions,
+        [
+            "операнд в выходную очередь",
+            "оператор в стек",
+            "операнд в выходную очередь",
+            "оператор в стек",
+            "операнд в выходную очередь",
+            "* из стека в выходную очередь",
+            "+ из стека в выходную очередь",
+        ]
+    );
+
+    let last = trace.conversion.last().unwrap();
+    assert_eq!((last.2.as_str(), last.3.as_str()), ("", "1 2 3 × +"));
+}
+
+#[test]
+fn test_convert_power_associativity() {
+    // -2^2 => 2 2 pow NEG, 2^3^2 => 2 3 2 pow pow
+    let cases = [
+        (
+            token_list(&[
+                (TokenType::UnaryOperator, "-"),
+                (TokenType::NumberInt, "2"),
+                (TokenType::BinaryOperator, "^"),
+                (TokenType::NumberInt, "2"),
+            ]),
+            "2 2 pow NEG",
+        ),
+        (
+            token_list(&[
+                (TokenType::NumberInt, "2"),
+                (TokenType::BinaryOperator, "^"),
+                (TokenType::NumberInt, "3"),
+                (TokenType::BinaryOperator, "^"),
+                (TokenType::NumberInt, "2"),
+            ]),
+            "2 3 2 pow pow",
+        ),
+    ];
+
+    for (tokens, expected) in cases {
+        let expr = InfixToAst::new().convert(tokens).unwrap();
+        assert_eq!(expr.to_rpn(), expected);
+    }
+}
+
+#[test]
+fn test_convert_conditional() {
+    // a ? b : c ? 1 : 2+3 => a b c 1 2 3 + ?: ?:
+    let test_tokens = token_list(&[
+        (TokenType::Identifier, "a"),
+        (TokenType::QuestionMark, "?"),
+        (TokenType::Identifier, "b"),
+        (TokenType::Colon, ":"),
+        (TokenType::Identifier, "c"),
+        (TokenType::QuestionMark, "?"),
+        (TokenType::NumberInt, "1"),
+        (TokenType::Colon, ":"),
+        (TokenType::NumberInt, "2"),
+        (TokenType::BinaryOperator, "+"),
+        (TokenType::NumberInt, "3"),
+    ]);
+
+    let expr = InfixToAst::new().convert(test_tokens).unwrap();
+    assert_eq!(expr.to_rpn(), "a b c 1 2 3 + ?: ?:");
+    assert_eq!(expr.to_string(), "a ? b : c ? 1 : 2 + 3");
+
+    // if(a, 1, 2) => a 1 2 ?:
+    let test_tokens = token_list(&[
+        (TokenType::Function, "if"),
+        (TokenType::OpenedParenthesis, "("),
+        (TokenType::Identifier, "a"),
+        (TokenType::ArgumentSeparator, ","),
+        (TokenType::NumberInt, "1"),
+        (TokenType::ArgumentSeparator, ","),
+        (TokenType::NumberInt, "2"),
+        (TokenType::ClosedParenthesis, ")"),
+    ]);
+    assert_eq!(InfixToAst::new().convert(test_tokens).unwrap().to_rpn(), "a 1 2 ?:");
 
-    assert!(test_converter.convert(test_tokens).is_err());
+    // a ? 1 => пропущен разделитель ветвей
+    let test_tokens = token_list(&[
+        (TokenType::Identifier, "a"),
+        (TokenType::QuestionMark, "?"),
+        (TokenType::NumberInt, "1"),
+    ]);
+    assert_eq!(InfixToRPN::new().to_rpn(test_tokens).err().unwrap().span(), Some(Span::new(1, 2)));
 }
@@ -0,0 +1,62 @@
+//!
+//! Пользовательские функции, определяемые в ходе сеанса работы калькулятора: `f(x, y) = x^2 + y`
+//!
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::ast::Expr;
+
+///
+/// Пользовательская функция: имя, параметры и тело в виде дерева выражения
+/// Параметры видны только в теле функции и перекрывают переменные с теми же именами
+///
+pub struct UserFunction {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Rc<Expr>,
+}
+
+// Печать определения функции в том виде, в котором оно вводится
+impl fmt::Display for UserFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({}) = {}", self.name, self.params.join(", "), self.body)
+    }
+}
+
+///
+/// Определения пользовательских функций сеанса, упорядоченные по имени
+///
+#[derive(Default)]
+pub struct Definitions {
+    functions: BTreeMap<String, UserFunction>,
+}
+
+// Реализация методов определений пользовательских функций
+impl Definitions {
+    pub fn new() -> Self {
+        Definitions::default()
+    }
+
+    ///
+    /// Определение функции. Новое определение заменяет прежнее с тем же именем
+    /// Возвращает true, если функция была переопределена
+    ///
+    pub fn define(&mut self, function: UserFunction) -> bool {
+        self.functions.insert(function.name.clone(), function).is_some()
+    }
+
+    ///
+    /// Поиск функции по имени
+    ///
+    pub fn get(&self, name: &str) -> Option<&UserFunction> {
+        self.functions.get(name)
+    }
+
+    ///
+    /// Все определенные функции в порядке имен
+    ///
+    pub fn list(&self) -> impl Iterator<Item = &UserFunction> {
+        self.functions.values()
+    }
+}
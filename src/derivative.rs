@@ -0,0 +1,537 @@
+//!
+//! Символьное дифференцирование дерева выражения: `d/dx(x^2 * sin(x))` = `2 * x * sin(x) + x ^ 2 * cos(x)`
+//! Производная упрощается при построении: нулевые слагаемые и единичные множители отбрасываются,
+//!   операции над числами выполняются сразу, одинаковые операнды сокращаются (`x / x` = `1`, `x * x` = `x ^ 2`)
+//!
+use std::cmp::Ordering;
+
+use bigdecimal::BigDecimal;
+
+use crate::ast::Expr;
+use crate::error::CalcError;
+use crate::number::Number;
+use crate::operator::Operator;
+use crate::registry::{OperatorRegistry, PERCENT_OPERATOR};
+use crate::token::{Span, TokenType};
+
+///
+/// Производная выражения по переменной
+/// Остальные переменные и константы считаются постоянными,
+///   тригонометрические функции дифференцируются в предположении, что углы заданы в радианах
+///
+pub fn differentiate(expr: &Expr, var: &str) -> Result<Expr, CalcError> {
+    let differentiator = Differentiator {
+        var,
+        registry: OperatorRegistry::standard(),
+    };
+    differentiator.derive(expr).map(|derivative| differentiator.simplify(&derivative))
+}
+
+// Построитель производной: правила дифференцирования и упрощающие конструкторы узлов дерева
+// Операторы новых узлов берутся из стандартного реестра, положение - от дифференцируемого узла
+struct Differentiator<'a> {
+    var: &'a str,
+    registry: OperatorRegistry,
+}
+
+// Операнд вместе с его производной
+type Derived<'e> = (&'e Expr, Result<Expr, CalcError>);
+
+// Реализация правил дифференцирования
+// Дерево обходится сверткой с явным стеком: правило для узла получает производные его потомков
+//   и использует только нужные, поэтому ошибка в неиспользуемой производной не прерывает дифференцирование
+impl Differentiator<'_> {
+    // Производная дерева
+    fn derive(&self, expr: &Expr) -> Result<Expr, CalcError> {
+        expr.fold(|node, derivatives| self.derive_node(node, derivatives))
+    }
+
+    // Производная узла дерева по производным его потомков
+    fn derive_node(&self, expr: &Expr, derivatives: Vec<Result<Expr, CalcError>>) -> Result<Expr, CalcError> {
+        let span = expr.span();
+        let mut derivatives = derivatives.into_iter();
+        let mut next = || derivatives.next().expect("производная потомка узла");
+        match expr {
+            Expr::Num(..) | Expr::Const(..) => Ok(self.num(0, span)),
+            Expr::Var(name, _) => Ok(self.num(if name == self.var { 1 } else { 0 }, span)),
+            Expr::Bool(..) => Err(CalcError::Differentiation(
+                "логическое значение не дифференцируется".to_string(),
+                span,
+            )),
+            Expr::Unary(op, arg, _) => {
+                let du = next()?;
+                match op.name() {
+                    "POS" => Ok(du),
+                    "NEG" => Ok(self.neg(du, span)),
+                    "sqrt" => Ok(self.div(du, self.mul(self.num(2, span), self.call("sqrt", arg, span), span), span)),
+                    PERCENT_OPERATOR => Ok(self.div(du, self.num(100, span), span)),
+                    _ => Err(unsupported("оператора", op.symbol(), span)),
+                }
+            }
+            Expr::Binary(op, lhs, rhs, _) => self.derive_binary(op, (lhs, next()), (rhs, next()), span),
+            Expr::Call(op, args, _) => self.derive_call(op, args, derivatives.collect(), span),
+            Expr::Cond(cond, _, _, _) => {
+                // условие не дифференцируется
+                let _ = next();
+                Ok(self.cond(cond.as_ref().clone(), next()?, next()?, span))
+            }
+        }
+    }
+
+    // Упрощение построенной производной снизу вверх теми же конструкторами узлов,
+    //   в том числе частей, скопированных из исходного выражения: `x * 2^3` => `8`
+    // Процент в правой части сложения и вычитания не упрощается, так как зависит от левой части
+    fn simplify(&self, expr: &Expr) -> Expr {
+        expr.fold(|node, children| {
+            let span = node.span();
+            let mut children = children.into_iter();
+            let mut next = || children.next().expect("упрощенный потомок узла");
+            match node {
+                Expr::Unary(op, _, _) if op.name() == "NEG" => self.neg(next(), span),
+                Expr::Unary(op, _, _) => Expr::Unary(op.clone(), Box::new(next()), span),
+                Expr::Binary(op, _, _, _) => {
+                    let (u, v) = (next(), next());
+                    match op.name() {
+                        _ if matches!(&v, Expr::Unary(percent, _, _) if percent.name() == PERCENT_OPERATOR) => {
+                            Expr::Binary(op.clone(), Box::new(u), Box::new(v), span)
+                        }
+                        "+" => self.add(u, v, span),
+                        "-" => self.sub(u, v, span),
+                        "×" => self.mul(u, v, span),
+                        "/" => self.div(u, v, span),
+                        "pow" => self.pow(u, v, span),
+                        _ => Expr::Binary(op.clone(), Box::new(u), Box::new(v), span),
+                    }
+                }
+                Expr::Call(op, _, _) => Expr::Call(op.clone(), children.collect(), span),
+                Expr::Cond(..) => self.cond(next(), next(), next(), span),
+                expr => expr.clone(),
+            }
+        })
+    }
+
+    // Производная бинарного оператора по операндам и их производным
+    // Процент в правой части сложения и вычитания берется от левой части: `a + b%` = `a + a * b / 100`
+    fn derive_binary(&self, op: &Operator, lhs: Derived, rhs: Derived, span: Span) -> Result<Expr, CalcError> {
+        let ((u, du), (v, dv)) = (lhs, rhs);
+        if let (Some('+' | '-'), Expr::Unary(percent, share, _)) = (op.name().chars().next(), v) {
+            if percent.name() == PERCENT_OPERATOR {
+                // `(a * b / 100)'` = `a' * b / 100 + a * (b%)'`
+                let du = du?;
+                let part = self.div(self.mul(du.clone(), share.as_ref().clone(), span), self.num(100, span), span);
+                let dpart = self.add(part, self.mul(u.clone(), dv?, span), span);
+                return Ok(match op.name() {
+                    "+" => self.add(du, dpart, span),
+                    _ => self.sub(du, dpart, span),
+                });
+            }
+        }
+
+        match op.name() {
+            "+" => Ok(self.add(du?, dv?, span)),
+            "-" => Ok(self.sub(du?, dv?, span)),
+            "×" => {
+                let lhs = self.mul(du?, v.clone(), span);
+                let rhs = self.mul(u.clone(), dv?, span);
+                Ok(self.add(lhs, rhs, span))
+            }
+            "/" => self.derive_quotient((u, du), (v, dv), span),
+            "pow" => self.derive_pow((u, du), (v, dv), span),
+            _ => Err(unsupported("оператора", op.symbol(), span)),
+        }
+    }
+
+    // Производная частного, для постоянного знаменателя - без возведения в квадрат
+    fn derive_quotient(&self, lhs: Derived, rhs: Derived, span: Span) -> Result<Expr, CalcError> {
+        let ((u, du), (v, dv)) = (lhs, rhs);
+        if !self.depends(v) {
+            return Ok(self.div(du?, v.clone(), span));
+        }
+
+        let lhs = self.mul(du?, v.clone(), span);
+        let rhs = self.mul(u.clone(), dv?, span);
+        let square = self.pow(v.clone(), self.num(2, span), span);
+        Ok(self.div(self.sub(lhs, rhs, span), square, span))
+    }
+
+    // Производная степени: отдельно для постоянного показателя, постоянного основания и общего случая
+    fn derive_pow(&self, lhs: Derived, rhs: Derived, span: Span) -> Result<Expr, CalcError> {
+        let ((u, du), (v, dv)) = (lhs, rhs);
+        if !self.depends(v) {
+            let exponent = self.sub(v.clone(), self.num(1, span), span);
+            let factor = self.mul(v.clone(), self.pow(u.clone(), exponent, span), span);
+            return Ok(self.mul(factor, du?, span));
+        }
+
+        let power = self.pow(u.clone(), v.clone(), span);
+        if !self.depends(u) {
+            let factor = self.mul(power, self.ln(u, span), span);
+            return Ok(self.mul(factor, dv?, span));
+        }
+
+        let lhs = self.mul(dv?, self.ln(u, span), span);
+        let rhs = self.div(self.mul(v.clone(), du?, span), u.clone(), span);
+        Ok(self.mul(power, self.add(lhs, rhs, span), span))
+    }
+
+    // Производная вызова функции по правилу дифференцирования сложной функции
+    // Тело пользовательской функции не раскрывается, поэтому ее производная, как и производная
+    //   неизвестной функции, не определена
+    fn derive_call(
+        &self,
+        op: &Operator,
+        args: &[Expr],
+        derivatives: Vec<Result<Expr, CalcError>>,
+        span: Span,
+    ) -> Result<Expr, CalcError> {
+        let name = op.name();
+        if let Operator::Unknown(_) = op {
+            return Err(CalcError::Differentiation(
+                format!("функция '{}' не является встроенной, ее производная не определена", name),
+                span,
+            ));
+        }
+        let mut derivatives = derivatives.into_iter();
+        if let Operator::BinaryFunction(_) = op {
+            return match (name, args, derivatives.next(), derivatives.next()) {
+                ("pow", [u, v], Some(du), Some(dv)) => self.derive_pow((u, du), (v, dv), span),
+                // `log(u, base)` = `ln(u) / ln(base)`, `ln(u)'` = `u' / u`
+                ("log", [u, base], Some(du), Some(dbase)) => {
+                    let dln = |u: &Expr, du: Result<Expr, CalcError>| {
+                        du.map(|du| self.mul(self.div(self.num(1, span), u.clone(), span), du, span))
+                    };
+                    let (ln_u, ln_base) = (self.ln(u, span), self.ln(base, span));
+                    self.derive_quotient((&ln_u, dln(u, du)), (&ln_base, dln(base, dbase)), span)
+                }
+                _ => Err(unsupported("функции", name, span)),
+            };
+        }
+
+        let (u, du) = match (op, args, derivatives.next()) {
+            (Operator::Function(_), [u], Some(du)) => (u, du),
+            _ => return Err(unsupported("функции", name, span)),
+        };
+        let one = || self.num(1, span);
+        let square = || self.pow(u.clone(), self.num(2, span), span);
+        let outer = match name {
+            "sin" => self.call("cos", u, span),
+            "cos" => self.neg(self.call("sin", u, span), span),
+            "tan" => self.div(one(), self.pow(self.call("cos", u, span), self.num(2, span), span), span),
+            "asin" => self.div(one(), self.call("sqrt", &self.sub(one(), square(), span), span), span),
+            "acos" => self.neg(self.div(one(), self.call("sqrt", &self.sub(one(), square(), span), span), span), span),
+            "atan" => self.div(one(), self.add(one(), square(), span), span),
+            "sqrt" => self.div(one(), self.mul(self.num(2, span), self.call("sqrt", u, span), span), span),
+            "cbrt" => {
+                let root = self.pow(self.call("cbrt", u, span), self.num(2, span), span);
+                self.div(one(), self.mul(self.num(3, span), root, span), span)
+            }
+            "exp" => self.call("exp", u, span),
+            "ln" => self.div(one(), u.clone(), span),
+            "log10" => self.div(one(), self.mul(u.clone(), self.ln(&self.num(10, span), span), span), span),
+            "log2" => self.div(one(), self.mul(u.clone(), self.ln(&self.num(2, span), span), span), span),
+            "abs" => self.call("sign", u, span),
+            _ => return Err(unsupported("функции", name, span)),
+        };
+
+        Ok(self.mul(outer, du?, span))
+    }
+
+    // Зависит ли выражение от переменной дифференцирования
+    // Вызов пользовательской функции считается зависящим, так как ее тело не раскрывается
+    fn depends(&self, expr: &Expr) -> bool {
+        let mut nodes = vec![expr];
+        while let Some(node) = nodes.pop() {
+            match node {
+                Expr::Var(name, _) if name == self.var => return true,
+                Expr::Call(Operator::Unknown(_), _, _) => return true,
+                node => nodes.extend(node.children()),
+            }
+        }
+        false
+    }
+}
+
+// Реализация упрощающих конструкторов узлов дерева
+impl Differentiator<'_> {
+    // Число. Отрицательное число записывается унарным минусом, чтобы печать `-1 ^ 2` не меняла смысл
+    fn number(&self, value: Number, span: Span) -> Expr {
+        match value.compare(&decimal(0)) {
+            Some(Ordering::Less) => match value.clone().neg() {
+                Ok(abs) => self.unary("-", Expr::Num(abs, span), span),
+                Err(_) => Expr::Num(value, span),
+            },
+            _ => Expr::Num(value, span),
+        }
+    }
+
+    fn num(&self, value: i64, span: Span) -> Expr {
+        Expr::Num(decimal(value), span)
+    }
+
+    // `--u` = `u`, `-(число)` вычисляется
+    fn neg(&self, u: Expr, span: Span) -> Expr {
+        match &u {
+            Expr::Unary(op, _, _) if op.name() == "NEG" => operand(u),
+            Expr::Num(value, _) => match value.clone().neg() {
+                Ok(negated) => self.number(negated, span),
+                Err(_) => self.unary("-", Expr::Num(value.clone(), span), span),
+            },
+            _ => self.unary("-", u, span),
+        }
+    }
+
+    // `0 + v` = `v`, `u + 0` = `u`, `u + u` = `2 * u`, `u + (-v)` = `u - v`
+    fn add(&self, u: Expr, v: Expr, span: Span) -> Expr {
+        if is_number(&u, 0) {
+            return v;
+        }
+        if is_number(&v, 0) {
+            return u;
+        }
+        if let Some(value) = fold(&u, &v, Number::add) {
+            return self.number(value, span);
+        }
+        if same(&u, &v) {
+            return self.mul(self.num(2, span), u, span);
+        }
+        match &v {
+            Expr::Unary(op, _, _) if op.name() == "NEG" => self.sub(u, operand(v), span),
+            _ => self.binary("+", u, v, span),
+        }
+    }
+
+    // `u - 0` = `u`, `0 - v` = `-v`, `u - u` = `0`, `u - (-v)` = `u + v`
+    fn sub(&self, u: Expr, v: Expr, span: Span) -> Expr {
+        if is_number(&v, 0) {
+            return u;
+        }
+        if is_number(&u, 0) {
+            return self.neg(v, span);
+        }
+        if let Some(value) = fold(&u, &v, Number::sub) {
+            return self.number(value, span);
+        }
+        if same(&u, &v) {
+            return self.num(0, span);
+        }
+        match &v {
+            Expr::Unary(op, _, _) if op.name() == "NEG" => self.add(u, operand(v), span),
+            _ => self.binary("-", u, v, span),
+        }
+    }
+
+    // `0 * v` = `0`, `1 * v` = `v`, `u * u` = `u ^ 2`,
+    //   числовой множитель выносится вперед: `u * 2` = `2 * u`, `u * (2 * v)` = `2 * u * v`
+    fn mul(&self, u: Expr, v: Expr, span: Span) -> Expr {
+        if is_number(&u, 0) || is_number(&v, 0) {
+            return self.num(0, span);
+        }
+        if is_number(&u, 1) {
+            return v;
+        }
+        if is_number(&v, 1) {
+            return u;
+        }
+        if let Some(value) = fold(&u, &v, Number::mul) {
+            return self.number(value, span);
+        }
+        if same(&u, &v) {
+            return self.pow(u, self.num(2, span), span);
+        }
+        match (&u, &v) {
+            (Expr::Unary(op, _, _), _) if op.name() == "NEG" => {
+                let product = self.mul(operand(u), v, span);
+                self.neg(product, span)
+            }
+            (_, Expr::Unary(op, _, _)) if op.name() == "NEG" => {
+                let product = self.mul(operand(v), u, span);
+                self.neg(product, span)
+            }
+            (_, Expr::Binary(op, lhs, _, _)) if op.name() == "×" && matches!(**lhs, Expr::Num(..)) => {
+                let (lhs, rhs) = operands(v);
+                let head = self.mul(lhs, u, span);
+                self.mul(head, rhs, span)
+            }
+            (_, Expr::Num(..)) => self.binary("*", v, u, span),
+            _ => self.binary("*", u, v, span),
+        }
+    }
+
+    // `0 / v` = `0`, `u / 1` = `u`, `u / u` = `1`, частное чисел вычисляется, если оно точное: `6 / 4` = `1.5`
+    fn div(&self, u: Expr, v: Expr, span: Span) -> Expr {
+        if is_number(&u, 0) {
+            return self.num(0, span);
+        }
+        if is_number(&v, 1) {
+            return u;
+        }
+        if same(&u, &v) {
+            return self.num(1, span);
+        }
+        if let (Some(value), Expr::Num(lhs, _), Expr::Num(rhs, _)) = (fold(&u, &v, Number::div), &u, &v) {
+            if value.clone().mul(rhs.clone()).is_ok_and(|product| product.equals(lhs)) {
+                return self.number(value, span);
+            }
+        }
+        self.binary("/", u, v, span)
+    }
+
+    // `u ^ 0` = `1`, `u ^ 1` = `u`, целая степень числа вычисляется: `2 ^ 3` = `8`
+    fn pow(&self, u: Expr, v: Expr, span: Span) -> Expr {
+        if is_number(&v, 0) {
+            return self.num(1, span);
+        }
+        if is_number(&v, 1) {
+            return u;
+        }
+        if matches!(&v, Expr::Num(exp, _) if exp.to_integer().is_ok()) {
+            if let Some(value) = fold(&u, &v, Number::pow) {
+                return self.number(value, span);
+            }
+        }
+        self.binary("^", u, v, span)
+    }
+
+    // Условное выражение с одинаковыми ветвями равно ветви: `x > 0 ? 0 : 0` = `0`
+    fn cond(&self, cond: Expr, then: Expr, otherwise: Expr, span: Span) -> Expr {
+        if same(&then, &otherwise) {
+            return then;
+        }
+        Expr::Cond(Box::new(cond), Box::new(then), Box::new(otherwise), span)
+    }
+
+    // Натуральный логарифм, `ln(e)` = `1`
+    fn ln(&self, u: &Expr, span: Span) -> Expr {
+        match u {
+            Expr::Const(name, _, _) if name == "e" => self.num(1, span),
+            u => self.call("ln", u, span),
+        }
+    }
+
+    fn call(&self, name: &str, arg: &Expr, span: Span) -> Expr {
+        let op = Operator::from_registry(&self.registry, &(TokenType::Function, name.to_string(), span));
+        Expr::Call(op, vec![arg.clone()], span)
+    }
+
+    fn unary(&self, symbol: &str, arg: Expr, span: Span) -> Expr {
+        let op = Operator::from_registry(&self.registry, &(TokenType::UnaryOperator, symbol.to_string(), span));
+        Expr::Unary(op, Box::new(arg), span)
+    }
+
+    fn binary(&self, symbol: &str, lhs: Expr, rhs: Expr, span: Span) -> Expr {
+        let op = Operator::from_registry(&self.registry, &(TokenType::BinaryOperator, symbol.to_string(), span));
+        Expr::Binary(op, Box::new(lhs), Box::new(rhs), span)
+    }
+}
+
+// Точное десятичное число, в том же виде, в каком разбираются литералы
+fn decimal(value: i64) -> Number {
+    Number::Decimal(BigDecimal::from(value))
+}
+
+// Является ли узел заданным числом
+fn is_number(expr: &Expr, value: i64) -> bool {
+    matches!(expr, Expr::Num(number, _) if number.equals(&decimal(value)))
+}
+
+// Совпадают ли выражения: узлы одного вида с одинаковыми операторами, именами, числами и операндами
+fn same(u: &Expr, v: &Expr) -> bool {
+    let mut pairs = vec![(u, v)];
+    while let Some((u, v)) = pairs.pop() {
+        let equal = match (u, v) {
+            (Expr::Num(x, _), Expr::Num(y, _)) => x.equals(y),
+            (Expr::Bool(x, _), Expr::Bool(y, _)) => x == y,
+            (Expr::Var(x, _), Expr::Var(y, _)) | (Expr::Const(x, _, _), Expr::Const(y, _, _)) => x == y,
+            (Expr::Unary(op, _, _), Expr::Unary(other, _, _))
+            | (Expr::Binary(op, _, _, _), Expr::Binary(other, _, _, _))
+            | (Expr::Call(op, _, _), Expr::Call(other, _, _)) => op.name() == other.name(),
+            (Expr::Cond(..), Expr::Cond(..)) => true,
+            _ => false,
+        };
+        let (xs, ys) = (u.children(), v.children());
+        if !equal || xs.len() != ys.len() {
+            return false;
+        }
+        pairs.extend(xs.into_iter().zip(ys));
+    }
+    true
+}
+
+// Аргумент унарного оператора
+fn operand(expr: Expr) -> Expr {
+    expr.into_children().remove(0)
+}
+
+// Операнды бинарного оператора (левый, правый)
+fn operands(expr: Expr) -> (Expr, Expr) {
+    let mut children = expr.into_children();
+    let rhs = children.pop().expect("правый операнд");
+    (children.pop().expect("левый операнд"), rhs)
+}
+
+// Вычисление операции над двумя числами, если оба операнда являются числами
+fn fold(u: &Expr, v: &Expr, f: fn(Number, Number) -> Result<Number, String>) -> Option<Number> {
+    match (u, v) {
+        (Expr::Num(lhs, _), Expr::Num(rhs, _)) => f(lhs.clone(), rhs.clone()).ok(),
+        _ => None,
+    }
+}
+
+// Ошибка для оператора или функции, для которых производная не определена
+fn unsupported(kind: &str, name: &str, span: Span) -> CalcError {
+    CalcError::Differentiation(format!("производная {} '{}' не определена", kind, name), span)
+}
+
+// Базовые тесты
+#[cfg(test)]
+fn derivative(input: &str) -> String {
+    differentiate(&crate::parse(input).unwrap(), "x").unwrap().to_string()
+}
+
+#[test]
+fn test_differentiate() {
+    assert_eq!(derivative("x^2 * sin(x)"), "2 * x * sin(x) + x ^ 2 * cos(x)");
+    assert_eq!(derivative("3*x + y - 5"), "3");
+    assert_eq!(derivative("y * x^3"), "3 * y * x ^ 2");
+    assert_eq!(derivative("x^3 / 4"), "3 * x ^ 2 / 4");
+    assert_eq!(derivative("1 / x"), "-1 / x ^ 2");
+    assert_eq!(derivative("cos(2*x)"), "-(2 * sin(2 * x))");
+    assert_eq!(derivative("e^x"), "e ^ x");
+    assert_eq!(derivative("2^x"), "2 ^ x * ln(2)");
+    assert_eq!(derivative("ln(x) - -x"), "1 / x + 1");
+    assert_eq!(derivative("x > 0 ? x : -x"), "x > 0 ? 1 : -1");
+}
+
+#[test]
+fn test_differentiate_simplification() {
+    assert_eq!(derivative("x * 2^3 + x / (3 * 4)"), "8 + 1 / 12");
+    assert_eq!(derivative("x * 6 / 4"), "1.5");
+    assert_eq!(derivative("x^x"), "x ^ x * (ln(x) + 1)");
+    assert_eq!(derivative("x * x"), "2 * x");
+    assert_eq!(derivative("exp(x) * exp(x)"), "2 * exp(x) ^ 2");
+    assert_eq!(derivative("x > 0 ? 2*x : x + x"), "2");
+    assert_eq!(derivative("y > 0 ? y : 1"), "0");
+}
+
+#[test]
+fn test_differentiate_errors() {
+    let error = differentiate(&crate::parse("floor(x)").unwrap(), "x").err().unwrap();
+    assert_eq!(error.to_string(), "Ошибка дифференцирования: производная функции 'floor' не определена");
+    assert_eq!(
+        differentiate(&crate::parse("2 * q(x)").unwrap(), "x").err(),
+        Some(CalcError::Differentiation(
+            "функция 'q' не является встроенной, ее производная не определена".to_string(),
+            Span::new(4, 5),
+        ))
+    );
+    assert!(matches!(
+        differentiate(&crate::parse("x == 1").unwrap(), "x"),
+        Err(CalcError::Differentiation(..))
+    ));
+}
+
+#[test]
+fn test_differentiate_deep_tree() {
+    assert_eq!(derivative(&format!("x{}", " + x".repeat(5_000))), "5001");
+    assert_eq!(derivative(&format!("{}x", "-".repeat(5_001))), "-1");
+}
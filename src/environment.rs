@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+
+use crate::value::Value;
+
+// Имя переменной, в которой автоматически сохраняется результат последнего вычисления
+pub const LAST_RESULT: &str = "ans";
+
+///
+/// Окружение сеанса работы калькулятора
+/// Хранит значения переменных (числа и логические значения) между вычислениями выражений
+///
+pub struct Environment {
+    variables: HashMap<String, Value>,
+}
+
+// Имплементация типажа для возможности создания объекта со значениями по-умолчанию
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Реализация методов окружения
+impl Environment {
+    pub fn new() -> Self {
+        Environment {
+            variables: HashMap::new(),
+        }
+    }
+
+    ///
+    /// Получение значения переменной по имени
+    ///
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.variables.get(name)
+    }
+
+    ///
+    /// Установка (переопределение) значения переменной
+    ///
+    pub fn set(&mut self, name: &str, value: impl Into<Value>) {
+        self.variables.insert(name.to_string(), value.into());
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt;
+
+use crate::token::Span;
+
+///
+/// Ошибка, возникшая на одном из этапов работы калькулятора
+/// Все ошибки, кроме ошибки чтения, содержат положение ошибочного фрагмента во входной строке
+///   - Config     - ошибка настройки калькулятора (например, конфликт пользовательского оператора со встроенным)
+///   - Read       - ошибка получения входной строки
+///   - Lexical    - неизвестная лексема
+///   - Validation - нарушение правил валидатора
+///   - Conversion - ошибка преобразования выражения (расстановка скобок, разделителей и т.п.)
+///   - Evaluation - ошибка вычисления выражения
+///   - Differentiation - ошибка символьного дифференцирования выражения
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcError {
+    Config(String),
+    Read(String),
+    Lexical(Span),
+    Validation(String, Span),
+    Conversion(String, Span),
+    Evaluation(String, Span),
+    Differentiation(String, Span),
+}
+
+// Реализация методов ошибки
+impl CalcError {
+    ///
+    /// Положение ошибочного фрагмента во входной строке
+    ///
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            CalcError::Config(_) | CalcError::Read(_) => None,
+            CalcError::Lexical(span)
+            | CalcError::Validation(_, span)
+            | CalcError::Conversion(_, span)
+            | CalcError::Evaluation(_, span)
+            | CalcError::Differentiation(_, span) => Some(*span),
+        }
+    }
+}
+
+// Вывод ошибки в текстовом виде
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::Config(why) => write!(f, "Ошибка настройки калькулятора: {}", why),
+            CalcError::Read(why) => write!(f, "Ошибка получения входной строки: {}", why),
+            CalcError::Lexical(_) => write!(f, "Ошибка разбиения на лексемы: неизвестная лексема"),
+            CalcError::Validation(why, _) => write!(f, "Ошибка валидации: {}", why),
+            CalcError::Conversion(why, _) => write!(f, "Ошибка преобразования: {}", why),
+            CalcError::Evaluation(why, _) => write!(f, "Ошибка вычисления: {}", why),
+            CalcError::Differentiation(why, _) => write!(f, "Ошибка дифференцирования: {}", why),
+        }
+    }
+}
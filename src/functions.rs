@@ -0,0 +1,278 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use num_complex::Complex64;
+
+use crate::number::Number;
+
+// Псевдонимы для сигнатур функций. Функциями могут быть и замыкания, в том числе пользовательские
+pub type MathFunction = Rc<dyn Fn(Number) -> Result<Number, String>>;
+pub type BinaryMathFunction = Rc<dyn Fn((Number, Number)) -> Result<Number, String>>;
+pub type VariadicMathFunction = Rc<dyn Fn(Vec<Number>) -> Result<Number, String>>;
+
+///
+/// Перечисление видов функций по количеству аргументов
+///   - Unary    - функции одного аргумента
+///   - Binary   - функции двух аргументов
+///   - Variadic - функции произвольного (не меньше одного) количества аргументов
+///
+#[derive(Clone)]
+pub enum Function {
+    Unary(MathFunction),
+    Binary(BinaryMathFunction),
+    Variadic(VariadicMathFunction),
+}
+
+// Создание функций из обычных функций и замыканий
+impl Function {
+    pub fn unary(f: impl Fn(Number) -> Result<Number, String> + 'static) -> Self {
+        Function::Unary(Rc::new(f))
+    }
+
+    pub fn binary(f: impl Fn((Number, Number)) -> Result<Number, String> + 'static) -> Self {
+        Function::Binary(Rc::new(f))
+    }
+
+    pub fn variadic(f: impl Fn(Vec<Number>) -> Result<Number, String> + 'static) -> Self {
+        Function::Variadic(Rc::new(f))
+    }
+}
+
+///
+/// Таблица встроенных математических функций: (имя функции, реализация)
+/// Для добавления новой функции достаточно дополнить таблицу
+///
+pub fn builtin_functions() -> Vec<(&'static str, Function)> {
+    vec![
+        ("sin", analytic("sin", f64::sin, Complex64::sin)),
+        ("cos", analytic("cos", f64::cos, Complex64::cos)),
+        ("tan", analytic("tan", f64::tan, Complex64::tan)),
+        ("asin", analytic("asin", f64::asin, Complex64::asin)),
+        ("acos", analytic("acos", f64::acos, Complex64::acos)),
+        ("atan", analytic("atan", f64::atan, Complex64::atan)),
+        ("sqrt", Function::unary(Number::sqrt)),
+        ("cbrt", analytic("cbrt", f64::cbrt, Complex64::cbrt)),
+        ("exp", analytic("exp", f64::exp, Complex64::exp)),
+        ("ln", analytic("ln", f64::ln, Complex64::ln)),
+        ("log10", analytic("log10", f64::log10, Complex64::log10)),
+        ("log2", analytic("log2", f64::log2, Complex64::log2)),
+        ("abs", Function::unary(Number::abs)),
+        ("floor", Function::unary(Number::floor)),
+        ("ceil", Function::unary(Number::ceil)),
+        ("round", Function::unary(Number::round)),
+        ("sign", Function::unary(Number::sign)),
+        ("arg", Function::unary(Number::arg)),
+        ("conj", Function::unary(Number::conj)),
+        ("re", Function::unary(Number::re)),
+        ("im", Function::unary(Number::im)),
+        ("pow", Function::binary(|(x, y)| x.pow(y))),
+        ("atan2", Function::binary(|(y, x)| y.apply_real2(x, "atan2", f64::atan2))),
+        ("log", Function::binary(|(x, base)| x.apply_real2(base, "log", f64::log))),
+        ("hypot", Function::binary(|(x, y)| x.apply_real2(y, "hypot", f64::hypot))),
+        ("min", Function::variadic(|args| extremum(args, Ordering::Less))),
+        ("max", Function::variadic(|args| extremum(args, Ordering::Greater))),
+        ("sum", Function::variadic(sum)),
+        ("avg", Function::variadic(avg)),
+        ("median", Function::variadic(median)),
+    ]
+}
+
+// Функция одного аргумента, определенная как для вещественных, так и для комплексных чисел
+fn analytic(name: &'static str, real: fn(f64) -> f64, complex: fn(Complex64) -> Complex64) -> Function {
+    Function::unary(move |x| x.apply_analytic(name, real, complex))
+}
+
+///
+/// Поиск встроенной функции по имени
+///
+pub fn find_function(name: &str) -> Option<Function> {
+    builtin_functions()
+        .into_iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, f)| f)
+}
+
+///
+/// Единицы измерения углов для тригонометрических функций
+///
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AngleUnit {
+    Radians,
+    Degrees,
+    Gradians,
+}
+
+// Функции, аргументом которых является угол
+const ANGLE_ARGUMENT: [&str; 3] = ["sin", "cos", "tan"];
+
+// Функции, результатом которых является угол
+const ANGLE_RESULT: [&str; 4] = ["asin", "acos", "atan", "atan2"];
+
+// Реализация методов единиц измерения углов
+impl AngleUnit {
+    ///
+    /// Получение единицы измерения по её обозначению: rad, deg, grad
+    ///
+    pub fn parse(name: &str) -> Option<AngleUnit> {
+        match name {
+            "rad" => Some(AngleUnit::Radians),
+            "deg" => Some(AngleUnit::Degrees),
+            "grad" => Some(AngleUnit::Gradians),
+            _ => None,
+        }
+    }
+
+    // Количество радиан в одной единице измерения
+    fn radians(&self) -> f64 {
+        match self {
+            AngleUnit::Radians => 1.0,
+            AngleUnit::Degrees => std::f64::consts::PI / 180.0,
+            AngleUnit::Gradians => std::f64::consts::PI / 200.0,
+        }
+    }
+
+    ///
+    /// Перевод в радианы аргументов функции, если её аргументом является угол
+    ///
+    pub fn convert_arguments(&self, function: &str, args: Vec<Number>) -> Result<Vec<Number>, String> {
+        if *self == AngleUnit::Radians || !ANGLE_ARGUMENT.contains(&function) {
+            return Ok(args);
+        }
+
+        args.into_iter()
+            .map(|x| Number::Float(x.to_f64() * self.radians()).into_kind_of(&x))
+            .collect()
+    }
+
+    ///
+    /// Перевод результата функции из радиан, если её результатом является угол
+    /// Результаты округляются (значения тригонометрических функций до 15 знаков после запятой,
+    ///   углы до 12 знаков), чтобы погрешность перевода не искажала точные значения (например, sin(180) = 0)
+    ///
+    pub fn convert_result(&self, function: &str, result: Number) -> Result<Number, String> {
+        if *self == AngleUnit::Radians {
+            return Ok(result);
+        }
+
+        let value = result.to_f64();
+        if ANGLE_RESULT.contains(&function) {
+            Number::Float(round_to(value / self.radians(), 12)).into_kind_of(&result)
+        } else if ANGLE_ARGUMENT.contains(&function) {
+            Number::Float(round_to(value, 15)).into_kind_of(&result)
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+// Округление до заданного количества знаков после запятой. Бесконечности не изменяются, -0 заменяется на 0
+fn round_to(value: f64, digits: i32) -> f64 {
+    if !value.is_finite() {
+        return value;
+    }
+
+    let scale = 10f64.powi(digits);
+    (value * scale).round() / scale + 0.0
+}
+
+// Поиск минимального (Less) или максимального (Greater) из аргументов
+fn extremum(args: Vec<Number>, wanted: Ordering) -> Result<Number, String> {
+    let mut iter = args.into_iter();
+    let mut result = iter.next().ok_or("нет аргументов")?;
+    for arg in iter {
+        match arg.compare(&result) {
+            Some(ord) if ord == wanted => result = arg,
+            Some(_) => {}
+            None => return Err(format!("значения {} и {} несравнимы", arg, result)),
+        }
+    }
+
+    Ok(result)
+}
+
+fn sum(args: Vec<Number>) -> Result<Number, String> {
+    let mut iter = args.into_iter();
+    let mut result = iter.next().ok_or("нет аргументов")?;
+    for arg in iter {
+        result = result.add(arg)?;
+    }
+
+    Ok(result)
+}
+
+fn avg(args: Vec<Number>) -> Result<Number, String> {
+    let count = Number::parse(&args.len().to_string())?;
+    sum(args)?.div(count)
+}
+
+// Медиана: среднее элемента отсортированного списка, либо среднее двух средних элементов
+fn median(mut args: Vec<Number>) -> Result<Number, String> {
+    if args.iter().any(|x| x.compare(x).is_none()) {
+        return Err("значение NaN не может участвовать в вычислении медианы".to_string());
+    }
+
+    args.sort_by(|x, y| x.compare(y).unwrap());
+    let middle = args.len() / 2;
+    if args.len() % 2 == 1 {
+        return Ok(args.swap_remove(middle));
+    }
+
+    avg(args.drain(middle - 1..=middle).collect())
+}
+
+// Базовые тесты
+#[cfg(test)]
+#[test]
+fn test_find_function() {
+    let sqrt = match find_function("sqrt") {
+        Some(Function::Unary(f)) => f,
+        _ => panic!("sqrt должна быть функцией одного аргумента"),
+    };
+    assert_eq!(sqrt(Number::Float(16.0)), Ok(Number::Float(4.0)));
+
+    assert!(find_function("unknown").is_none());
+}
+
+#[test]
+fn test_function_domain_error() {
+    if let Some(Function::Unary(ln)) = find_function("ln") {
+        assert!(ln(Number::Float(-1.0)).is_err());
+    }
+
+    if let Some(Function::Unary(sqrt)) = find_function("sqrt") {
+        assert!(sqrt(Number::parse("-4").unwrap()).is_err());
+    }
+}
+
+#[test]
+fn test_variadic_functions() {
+    let args = || {
+        vec![
+            Number::parse("3").unwrap(),
+            Number::parse("1").unwrap(),
+            Number::parse("4").unwrap(),
+            Number::parse("2").unwrap(),
+        ]
+    };
+
+    assert_eq!(extremum(args(), Ordering::Less).unwrap().to_string(), "1");
+    assert_eq!(extremum(args(), Ordering::Greater).unwrap().to_string(), "4");
+    assert_eq!(sum(args()).unwrap().to_string(), "10");
+    assert_eq!(avg(args()).unwrap().to_string(), "2.5");
+    assert_eq!(median(args()).unwrap().to_string(), "2.5");
+}
+
+#[test]
+fn test_angle_units() {
+    let degrees = AngleUnit::parse("deg").unwrap();
+    let args = degrees.convert_arguments("sin", vec![Number::Float(180.0)]).unwrap();
+    let result = Number::Float(args[0].to_f64().sin());
+    assert_eq!(degrees.convert_result("sin", result), Ok(Number::Float(0.0)));
+
+    let result = degrees.convert_result("acos", Number::Float(std::f64::consts::PI));
+    assert_eq!(result, Ok(Number::Float(180.0)));
+
+    // аргументы прочих функций не переводятся
+    let args = degrees.convert_arguments("sqrt", vec![Number::Float(180.0)]).unwrap();
+    assert_eq!(args, vec![Number::Float(180.0)]);
+}
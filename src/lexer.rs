@@ -1,13 +1,28 @@
 //!
+//! Разбиение входной строки на токены
+//!
+use std::rc::Rc;
+
 use regex::Regex;
 
-use crate::token::{TokenList, TokenType};
+use crate::error::CalcError;
+use crate::registry::OperatorRegistry;
+use crate::token::{Span, Token, TokenList, TokenType};
 
 ///
 /// Типаж для определения объектов реализующих разбиение строки на токены
 ///
 pub trait Lexer {
-    fn tokenize(&self, input: &str) -> Result<TokenList, char>;
+    fn tokenize(&self, input: &str) -> Result<TokenList, CalcError>;
+
+    ///
+    /// Создание аналогичного объекта, распознающего операторы заданного реестра
+    /// Используется строителем калькулятора при регистрации пользовательских операторов
+    /// Объекты, не зависящие от набора операторов, возвращают None
+    ///
+    fn with_operators(&self, _registry: &OperatorRegistry) -> Option<Rc<dyn Lexer>> {
+        None
+    }
 }
 
 // Объект заглушка
@@ -15,126 +30,495 @@ pub struct EmptyLexer {}
 
 // Пустая реализация для объекта заглушки
 impl Lexer for EmptyLexer {
-    fn tokenize(&self, _: &str) -> Result<TokenList, char> {
-        Err(' ')
+    fn tokenize(&self, _: &str) -> Result<TokenList, CalcError> {
+        Err(CalcError::Lexical(Span::default()))
     }
 }
 
+// Символы Unicode, встречающиеся в формулах из документов, и соответствующие им обозначения
+const UNICODE_ALIASES: [(char, &str); 6] = [('×', "*"), ('·', "*"), ('⋅', "*"), ('÷', "/"), ('−', "-"), ('π', "pi")];
+
+// Надстрочные знаки показателя степени: x² = x^2, x⁻¹ = x^-1
+const SUPERSCRIPTS: [(char, char); 11] = [
+    ('⁰', '0'),
+    ('¹', '1'),
+    ('²', '2'),
+    ('³', '3'),
+    ('⁴', '4'),
+    ('⁵', '5'),
+    ('⁶', '6'),
+    ('⁷', '7'),
+    ('⁸', '8'),
+    ('⁹', '9'),
+    ('⁻', '-'),
+];
+
 ///
 /// Объект токенизатор на основе регулярных выражений
+/// Символы Unicode (×, ÷, −, π, надстрочные показатели степени) заменяются токенами соответствующих операторов и констант
 ///
 pub struct RegexpLexer {
     knows_tokens: Vec<(TokenType, Regex)>,
+    aliases: Vec<(char, TokenType, String)>, // символ Unicode и токен, которым он заменяется
+    has_power: bool,                         // допустимы ли надстрочные показатели степени
+    postfix: Vec<(String, bool)>,            // обозначения постфиксных операторов и наличие бинарного варианта
+    constants: Vec<String>,                  // имена констант, которые разбираются не как переменные
+}
+
+// Имплементация типажа для возможности создания объекта со значениями по-умолчанию
+impl Default for RegexpLexer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 // Реализация методов для токенизатора на основе регулярных выражений
 impl RegexpLexer {
     ///
     /// Создает новый объект со списком известных токенов и соответствующих им регулярных выражений
+    /// Обозначения операторов берутся из реестра стандартных операторов
+    ///
     pub fn new() -> Self {
-        let mut list: Vec<(TokenType, Regex)> = Vec::new();
-        list.push((TokenType::OpenedParenthesis, Regex::new(r"^(\()").unwrap()));
-        list.push((TokenType::ClosedParenthesis, Regex::new(r"^(\))").unwrap()));
-        list.push((TokenType::Function, Regex::new(r"^[a-zA-Z]+").unwrap()));
-        list.push((
-            TokenType::BinaryOperator,
-            Regex::new(r"^(([/\*\^]{1,1})|(<{2,2})|(>{2,2}))").unwrap(),
-        ));
-        list.push((
-            TokenType::UnaryOperator,
-            Regex::new(r"^([\+\-]{1,1})").unwrap(),
-        ));
-        list.push((TokenType::NumberFloat, Regex::new(r"^(\d+\.\d+)").unwrap()));
-        list.push((TokenType::NumberInt, Regex::new(r"^(\d+)").unwrap()));
-        list.push((
-            TokenType::ArgumentSeparator,
-            Regex::new(r"^(,{1,1})").unwrap(),
-        ));
-        list.push((TokenType::Whitespaces, Regex::new(r"^(\s+)").unwrap()));
-
-        RegexpLexer { knows_tokens: list }
+        RegexpLexer::with_registry(&OperatorRegistry::standard())
+    }
+
+    ///
+    /// Создает новый объект, распознающий операторы заданного реестра
+    /// Обозначения операторов проверяются от более длинных к более коротким, чтобы, например,
+    ///   `<<` не был разобран как два оператора `<`. Обозначение, для которого в реестре есть унарный оператор,
+    ///   разбирается как унарный оператор (бинарность определяется валидатором), а если бинарного варианта
+    ///   у обозначения нет - как префиксный оператор
+    /// Обозначение постфиксного оператора после операнда разбирается как постфиксный оператор: `5!`, `10%`
+    /// Имена констант реестра разбираются в токены констант, остальные имена - в идентификаторы
+    ///
+    pub fn with_registry(registry: &OperatorRegistry) -> Self {
+        // Заменяются только символы операторов и констант, которые есть в реестре
+        let mut aliases: Vec<(char, TokenType, String)> = Vec::new();
+        for (symbol, target) in UNICODE_ALIASES.iter() {
+            let kind = if registry.find(target, 1).is_some() {
+                operator_kind(registry, target)
+            } else if registry.find(target, 2).is_some() {
+                TokenType::BinaryOperator
+            } else if registry.find_constant(target).is_some() {
+                TokenType::Constant
+            } else {
+                continue;
+            };
+            aliases.push((*symbol, kind, target.to_string()));
+        }
+
+        let mut operators: Vec<(TokenType, &str)> = Vec::new();
+        for op in registry.operators() {
+            if operators.iter().any(|(_, symbol)| *symbol == op.symbol) {
+                continue;
+            }
+            let kind = if registry.find(&op.symbol, 1).is_some() {
+                operator_kind(registry, &op.symbol)
+            } else if registry.find(&op.symbol, 2).is_some() {
+                TokenType::BinaryOperator
+            } else {
+                TokenType::PostfixOperator
+            };
+            operators.push((kind, &op.symbol));
+        }
+
+        // Обозначения, которые после операнда могут быть постфиксными операторами, и наличие у них бинарного варианта
+        let postfix: Vec<(String, bool)> = registry
+            .operators()
+            .iter()
+            .filter(|op| op.postfix)
+            .map(|op| (op.symbol.clone(), registry.find(&op.symbol, 2).is_some()))
+            .collect();
+        operators.sort_by_key(|(_, symbol)| std::cmp::Reverse(symbol.len()));
+
+        let mut list: Vec<(TokenType, Regex)> = vec![
+            (TokenType::OpenedParenthesis, Regex::new(r"^(\()").unwrap()),
+            (TokenType::ClosedParenthesis, Regex::new(r"^(\))").unwrap()),
+            (TokenType::QuestionMark, Regex::new(r"^(\?)").unwrap()),
+            (TokenType::Colon, Regex::new(r"^(:)").unwrap()),
+        ];
+
+        // Обозначения из букв должны заканчиваться на границе слова, чтобы не захватывать начало имени
+        for (kind, symbol) in operators {
+            let boundary = if symbol.ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                r"\b"
+            } else {
+                ""
+            };
+            list.push((kind, Regex::new(&format!("^({}){}", regex::escape(symbol), boundary)).unwrap()));
+        }
+
+        // Цифры числовых литералов могут разделяться символом `_`: 1_000_000, 0xFF_FF
+        // Мнимый литерал (`i`, `2i`, `0.5i`) проверяется до идентификаторов, граница слова отделяет его от имен (`im`)
+        list.extend(vec![
+            (TokenType::NumberRadix(16), Regex::new(r"^(0[xX][0-9a-fA-F]+(_[0-9a-fA-F]+)*)\b").unwrap()),
+            (TokenType::NumberRadix(2), Regex::new(r"^(0[bB][01]+(_[01]+)*)\b").unwrap()),
+            (TokenType::NumberRadix(8), Regex::new(r"^(0[oO][0-7]+(_[0-7]+)*)\b").unwrap()),
+            (
+                TokenType::NumberImaginary,
+                Regex::new(r"^(((\d+(_\d+)*(\.\d+(_\d+)*)?|\.\d+(_\d+)*)([eE][+-]?\d+)?)?i)\b").unwrap(),
+            ),
+            (TokenType::Boolean, Regex::new(r"^(true|false)\b").unwrap()),
+            (TokenType::Identifier, Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*").unwrap()),
+            (TokenType::Assignment, Regex::new(r"^(=)").unwrap()),
+            (
+                TokenType::NumberFloat,
+                Regex::new(r"^((\d+(_\d+)*)?\.\d+(_\d+)*([eE][+-]?\d+)?|\d+(_\d+)*[eE][+-]?\d+)").unwrap(),
+            ),
+            (TokenType::NumberInt, Regex::new(r"^(\d+(_\d+)*)").unwrap()),
+            (
+                TokenType::ArgumentSeparator,
+                Regex::new(r"^(,{1,1})").unwrap(),
+            ),
+            (TokenType::Whitespaces, Regex::new(r"^(\s+)").unwrap()),
+        ]);
+
+        RegexpLexer {
+            knows_tokens: list,
+            aliases,
+            has_power: registry.find("^", 2).is_some(),
+            postfix,
+            constants: registry.constants().iter().map(|(name, _)| name.clone()).collect(),
+        }
+    }
+
+    // Разбор символов Unicode в начале строки
+    // Возвращает количество разобранных байт, либо 0, если строка начинается не с известного символа Unicode
+    // Надстрочный показатель степени состоит из цифр, перед которыми допускается один знак минус,
+    //   иначе возвращается ошибка с положением всего показателя
+    fn tokenize_unicode(&self, input: &str, pos: usize, tokens: &mut TokenList) -> Result<usize, CalcError> {
+        let first = match input.chars().next() {
+            Some(c) => c,
+            None => return Ok(0),
+        };
+
+        if let Some((symbol, kind, target)) = self.aliases.iter().find(|(symbol, _, _)| *symbol == first) {
+            tokens.push((*kind, target.clone(), Span::new(pos, pos + symbol.len_utf8())));
+            return Ok(symbol.len_utf8());
+        }
+
+        // Надстрочный показатель степени разбирается в оператор возведения в степень и число
+        let superscript = |c: char| SUPERSCRIPTS.iter().find(|(s, _)| *s == c).map(|(_, d)| *d);
+        let exponent: String = input.chars().map_while(superscript).collect();
+        if !self.has_power || exponent.is_empty() {
+            return Ok(0);
+        }
+
+        let len: usize = input.chars().take(exponent.len()).map(char::len_utf8).sum();
+        let span = Span::new(pos, pos + len);
+        let digits = exponent.strip_prefix('-').unwrap_or(&exponent);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(CalcError::Lexical(span));
+        }
+
+        tokens.push((TokenType::BinaryOperator, "^".to_string(), span));
+        if digits.len() < exponent.len() {
+            tokens.push((TokenType::UnaryOperator, "-".to_string(), span));
+        }
+        tokens.push((TokenType::NumberInt, digits.to_string(), span));
+
+        Ok(len)
+    }
+
+    // Замена операторов после операнда на постфиксные операторы
+    // Если у обозначения есть бинарный вариант, то оператор постфиксный, только если за ним не следует операнд:
+    //   `10% + 1`, но `7 % 3`. Операнд может начинаться с префиксного оператора (`7 % √4`), либо со знака,
+    //   записанного вплотную к операнду и отдельно от оператора (`7 % -3`)
+    fn mark_postfix(&self, tokens: &mut TokenList) {
+        for ind in 1..tokens.len() {
+            let binary = match self.postfix.iter().find(|(symbol, _)| *symbol == tokens[ind].1) {
+                Some((_, binary)) if tokens[ind].0 != TokenType::PostfixOperator => *binary,
+                _ => continue,
+            };
+
+            let after_operand = ends_operand(tokens[ind - 1].0);
+            let is_sign = |next: &Token| {
+                next.0 == TokenType::UnaryOperator
+                    && tokens[ind].2.end < next.2.start
+                    && tokens.get(ind + 2).is_some_and(|after| next.2.end == after.2.start)
+            };
+            let before_operand = tokens.get(ind + 1).is_some_and(|next| {
+                next.0.is_number()
+                    || next.0 == TokenType::Boolean
+                    || next.0 == TokenType::Identifier
+                    || next.0 == TokenType::Constant
+                    || next.0 == TokenType::OpenedParenthesis
+                    || next.0 == TokenType::PrefixOperator
+                    || is_sign(next)
+            });
+
+            if after_operand && !(binary && before_operand) {
+                tokens[ind].0 = TokenType::PostfixOperator;
+            }
+        }
+    }
+}
+
+// Тип токена обозначения унарного оператора: унарный, если у обозначения есть бинарный вариант, иначе префиксный
+fn operator_kind(registry: &OperatorRegistry, symbol: &str) -> TokenType {
+    match registry.find(symbol, 2) {
+        Some(_) => TokenType::UnaryOperator,
+        None => TokenType::PrefixOperator,
     }
 }
 
+// Заканчивается ли операнд токеном данного типа: число, логический литерал, переменная, константа,
+//   закрывающая скобка или постфиксный оператор
+fn ends_operand(kind: TokenType) -> bool {
+    kind.is_number()
+        || kind == TokenType::Boolean
+        || kind == TokenType::Identifier
+        || kind == TokenType::Constant
+        || kind == TokenType::ClosedParenthesis
+        || kind == TokenType::PostfixOperator
+}
+
 // Реализация типажа токенизатора
 impl Lexer for RegexpLexer {
     ///
     /// Выполняет преобразование строки в список токенов
+    /// Пробельные символы пропускаются, каждый токен хранит своё положение во входной строке
     /// В случае успеха возвращает список токенов
-    /// В случае неудачи первый символ, который не соответствует ни одному известному токену
+    /// В случае неудачи ошибку с положением первого символа, который не соответствует ни одному известному токену
     ///
-    fn tokenize(&self, input: &str) -> Result<TokenList, char> {
+    fn tokenize(&self, input: &str) -> Result<TokenList, CalcError> {
         let mut tokens: TokenList = Vec::new();
-        let mut target_string: String = input.to_string();
-        let mut error: bool = false;
+        let mut pos: usize = 0;
 
-        while !target_string.is_empty() && !error {
-            let strlen_before = target_string.len();
-            for tok in &self.knows_tokens {
+        while pos < input.len() {
+            let target_string: &str = &input[pos..];
+            let mut matched: usize = self.tokenize_unicode(target_string, pos, &mut tokens)?;
+            for tok in self.knows_tokens.iter().filter(|_| matched == 0) {
                 let rgx: &Regex = &tok.1;
-                if let Some(captions) = rgx.captures(&target_string) {
-                    let value = &captions[0];
-                    tokens.push((tok.0, value.to_string()));
-                    target_string = target_string.strip_prefix(value).unwrap().to_string();
+                if let Some(found) = rgx.find(target_string) {
+                    let value = found.as_str();
+                    // Оператор, который бывает только постфиксным, не после операнда разбирается
+                    //   более коротким обозначением: `!!x` - двойное логическое отрицание, а не двойной факториал
+                    if tok.0 == TokenType::PostfixOperator && !tokens.last().is_some_and(|prev| ends_operand(prev.0)) {
+                        continue;
+                    }
+                    matched = value.len();
+                    let kind = match tok.0 {
+                        TokenType::Identifier if self.constants.iter().any(|name| name == value) => TokenType::Constant,
+                        kind => kind,
+                    };
+                    if kind != TokenType::Whitespaces {
+                        tokens.push((kind, value.to_string(), Span::new(pos, pos + matched)));
+                    }
                     break;
                 }
             }
 
-            // если после сопоставления всех известных шаблонов длина строки не изменилась,
-            //  то считаем произошла ошибка при разборе
-            error = strlen_before == target_string.len();
-        }
+            // если ни один из известных шаблонов не подошел, то считаем произошла ошибка при разборе
+            //   и возвращаем положение первого символа оставшейся строки, т.к. именно на нем разбор завершился
+            if matched == 0 {
+                let len = target_string.chars().next().unwrap().len_utf8();
+                return Err(CalcError::Lexical(Span::new(pos, pos + len)));
+            }
 
-        if error {
-            // в случае ошибки возвращаем первый символ оставщейся строки, т.к. именно на нем
-            //   разбор завершился
-            return Err(target_string.chars().next().unwrap());
+            pos += matched;
         }
 
+        self.mark_postfix(&mut tokens);
         Ok(tokens)
     }
+
+    fn with_operators(&self, registry: &OperatorRegistry) -> Option<Rc<dyn Lexer>> {
+        Some(Rc::new(RegexpLexer::with_registry(registry)))
+    }
 }
 
 // Базовые тесты
 #[cfg(test)]
 use std::collections::HashMap;
+#[cfg(test)]
+use crate::token::token_list;
 
 #[test]
 fn test_lexer_unkown_tokens() {
     let mut test_str = HashMap::new();
-    test_str.insert('&', "1      123 123123 & 123 213");
-    test_str.insert('=', "1      1 = 123123 & 123 213");
-    test_str.insert('!', "1      !13 123123 & 123 213");
+    test_str.insert('"', "1      123 123123 \" 123 213");
+    test_str.insert('$', "1      1 $ 123123 & 123 213");
+    test_str.insert('`', "1      `13 123123 & 123 213");
     test_str.insert('#', "1      1#3 123123 & 123 213");
     test_str.insert(';', "1      ;23 123123 & 123 213");
-    test_str.insert('%', "1      123 123123 % 123 213");
+    test_str.insert('@', "1      123 123123 @ 123 213");
 
     let lex: RegexpLexer = RegexpLexer::new();
     for (r, s) in test_str {
-        assert_eq!(lex.tokenize(s), Err(r));
+        let span = lex.tokenize(s).unwrap_err().span().unwrap();
+        assert_eq!(&s[span.start..span.end], r.to_string());
     }
 }
 
 #[test]
 fn lexer_all_known_tokens() {
     let test_str = "(1+-1.1)*2/3>>4<<5";
-    let expected: TokenList = vec![
-        (TokenType::OpenedParenthesis, "(".to_string()),
-        (TokenType::NumberInt, "1".to_string()),
-        (TokenType::UnaryOperator, "+".to_string()),
-        (TokenType::UnaryOperator, "-".to_string()),
-        (TokenType::NumberFloat, "1.1".to_string()),
-        (TokenType::ClosedParenthesis, ")".to_string()),
-        (TokenType::BinaryOperator, "*".to_string()),
-        (TokenType::NumberInt, "2".to_string()),
-        (TokenType::BinaryOperator, "/".to_string()),
-        (TokenType::NumberInt, "3".to_string()),
-        (TokenType::BinaryOperator, ">>".to_string()),
-        (TokenType::NumberInt, "4".to_string()),
-        (TokenType::BinaryOperator, "<<".to_string()),
-        (TokenType::NumberInt, "5".to_string()),
-    ];
+    let expected: TokenList = token_list(&[
+        (TokenType::OpenedParenthesis, "("),
+        (TokenType::NumberInt, "1"),
+        (TokenType::UnaryOperator, "+"),
+        (TokenType::UnaryOperator, "-"),
+        (TokenType::NumberFloat, "1.1"),
+        (TokenType::ClosedParenthesis, ")"),
+        (TokenType::BinaryOperator, "*"),
+        (TokenType::NumberInt, "2"),
+        (TokenType::BinaryOperator, "/"),
+        (TokenType::NumberInt, "3"),
+        (TokenType::BinaryOperator, ">>"),
+        (TokenType::NumberInt, "4"),
+        (TokenType::BinaryOperator, "<<"),
+        (TokenType::NumberInt, "5"),
+    ]);
+    let lex: RegexpLexer = RegexpLexer::new();
+
+    assert_eq!(lex.tokenize(test_str), Ok(expected));
+}
+
+#[test]
+fn lexer_comparison_and_logic() {
+    let lex: RegexpLexer = RegexpLexer::new();
+    let kinds: Vec<(TokenType, String)> = lex
+        .tokenize("x<=1==!truex||false")
+        .unwrap()
+        .into_iter()
+        .map(|(kind, value, _)| (kind, value))
+        .collect();
+
+    assert_eq!(kinds[1], (TokenType::BinaryOperator, "<=".to_string()));
+    assert_eq!(kinds[3], (TokenType::BinaryOperator, "==".to_string()));
+    assert_eq!(kinds[4], (TokenType::PrefixOperator, "!".to_string()));
+    assert_eq!(kinds[5], (TokenType::Identifier, "truex".to_string()));
+    assert_eq!(kinds[7], (TokenType::Boolean, "false".to_string()));
+}
+
+#[test]
+fn lexer_postfix_operators() {
+    let lex: RegexpLexer = RegexpLexer::new();
+    let kinds = |input: &str| -> Vec<TokenType> { lex.tokenize(input).unwrap().into_iter().map(|tok| tok.0).collect() };
+
+    assert_eq!(
+        kinds("5!!+!x")[1..4],
+        [TokenType::PostfixOperator, TokenType::UnaryOperator, TokenType::PrefixOperator]
+    );
+    assert_eq!(kinds("(1+2)! % 3")[5..7], [TokenType::PostfixOperator, TokenType::BinaryOperator]);
+    assert_eq!(kinds("200 + 10%")[3], TokenType::PostfixOperator);
+    assert_eq!(kinds("10% * (2)")[1], TokenType::PostfixOperator);
+    assert_eq!(kinds("5!=3")[1], TokenType::BinaryOperator);
+    assert_eq!(kinds("7 % -3")[1], TokenType::BinaryOperator);
+    assert_eq!(kinds("7 % √4")[1], TokenType::BinaryOperator);
+    assert_eq!(kinds("7 % ~1")[1], TokenType::BinaryOperator);
+    assert_eq!(kinds("10% - 3")[1], TokenType::PostfixOperator);
+    assert_eq!(kinds("10%-3")[1], TokenType::PostfixOperator);
+    assert_eq!(kinds("!!true")[..2], [TokenType::PrefixOperator, TokenType::PrefixOperator]);
+    assert_eq!(kinds("1 + !!x")[2..4], [TokenType::PrefixOperator, TokenType::PrefixOperator]);
+}
+
+#[test]
+fn lexer_identifiers_and_assignment() {
+    let test_str = "x_1=ans*2";
+    let expected: TokenList = token_list(&[
+        (TokenType::Identifier, "x_1"),
+        (TokenType::Assignment, "="),
+        (TokenType::Identifier, "ans"),
+        (TokenType::BinaryOperator, "*"),
+        (TokenType::NumberInt, "2"),
+    ]);
     let lex: RegexpLexer = RegexpLexer::new();
 
     assert_eq!(lex.tokenize(test_str), Ok(expected));
 }
+
+#[test]
+fn lexer_imaginary_literals() {
+    let lex: RegexpLexer = RegexpLexer::new();
+    let kinds: Vec<(TokenType, String)> = lex
+        .tokenize("2.5i+i*im(x)")
+        .unwrap()
+        .into_iter()
+        .map(|(kind, value, _)| (kind, value))
+        .collect();
+
+    assert_eq!(kinds[0], (TokenType::NumberImaginary, "2.5i".to_string()));
+    assert_eq!(kinds[2], (TokenType::NumberImaginary, "i".to_string()));
+    assert_eq!(kinds[4], (TokenType::Identifier, "im".to_string()));
+}
+
+#[test]
+fn lexer_literal_forms() {
+    let lex: RegexpLexer = RegexpLexer::new();
+    let kinds: Vec<(TokenType, String)> = lex
+        .tokenize("0xFF_FF+0b1010*0o17-1e-9/.5+1_000")
+        .unwrap()
+        .into_iter()
+        .filter(|(kind, _, _)| kind.is_number())
+        .map(|(kind, value, _)| (kind, value))
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            (TokenType::NumberRadix(16), "0xFF_FF".to_string()),
+            (TokenType::NumberRadix(2), "0b1010".to_string()),
+            (TokenType::NumberRadix(8), "0o17".to_string()),
+            (TokenType::NumberFloat, "1e-9".to_string()),
+            (TokenType::NumberFloat, ".5".to_string()),
+            (TokenType::NumberInt, "1_000".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn lexer_unicode_aliases() {
+    let lex: RegexpLexer = RegexpLexer::new();
+    let tokens = lex.tokenize("2×3÷−x²").unwrap();
+    let kinds: Vec<(TokenType, &str)> = tokens.iter().map(|(kind, value, _)| (*kind, value.as_str())).collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            (TokenType::NumberInt, "2"),
+            (TokenType::BinaryOperator, "*"),
+            (TokenType::NumberInt, "3"),
+            (TokenType::BinaryOperator, "/"),
+            (TokenType::UnaryOperator, "-"),
+            (TokenType::Identifier, "x"),
+            (TokenType::BinaryOperator, "^"),
+            (TokenType::NumberInt, "2"),
+        ]
+    );
+    // положение символов Unicode задается в байтах исходной строки
+    assert_eq!(tokens[1].2, Span::new(1, 3));
+    assert_eq!(tokens[7].2, Span::new(10, 12));
+
+    assert_eq!(lex.tokenize("x⁻¹²").unwrap().len(), 4);
+    assert!(lex.tokenize("x⁻").is_err());
+    assert_eq!(lex.tokenize("2²⁻").unwrap_err().span(), Some(Span::new(1, 6)));
+    assert!(lex.tokenize("2⁻⁻²").is_err());
+}
+
+#[test]
+fn lexer_constants() {
+    let lex: RegexpLexer = RegexpLexer::new();
+    let kinds: Vec<(TokenType, String)> = lex
+        .tokenize("2*π + pi_2 - k_B")
+        .unwrap()
+        .into_iter()
+        .map(|(kind, value, _)| (kind, value))
+        .collect();
+
+    assert_eq!(kinds[2], (TokenType::Constant, "pi".to_string()));
+    assert_eq!(kinds[4], (TokenType::Identifier, "pi_2".to_string()));
+    assert_eq!(kinds[6], (TokenType::Constant, "k_B".to_string()));
+}
+
+#[test]
+fn lexer_token_spans() {
+    let lex: RegexpLexer = RegexpLexer::new();
+    let tokens = lex.tokenize("12 +  x").unwrap();
+
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(tokens[0].2, Span::new(0, 2));
+    assert_eq!(tokens[1].2, Span::new(3, 4));
+    assert_eq!(tokens[2].2, Span::new(6, 7));
+}
@@ -0,0 +1,129 @@
+//!
+//! Калькулятор арифметических выражений: разбор инфиксной записи, преобразование в обратную
+//!   польскую нотацию алгоритмом сортировочной станции и вычисление
+//!
+//! Для простых случаев достаточно функций `evaluate`, `parse` и `to_rpn`.
+//! Для тонкой настройки калькулятор собирается через `CalculatorBuilder`,
+//!   которому передаются нужные реализации разборщика, валидатора, преобразователя, числовой системы и вывода
+//!
+use std::rc::Rc;
+
+pub mod ast;
+pub mod builder;
+pub mod calculator;
+pub mod constants;
+pub mod converters;
+pub mod definitions;
+pub mod derivative;
+pub mod environment;
+pub mod error;
+pub mod functions;
+pub mod lexer;
+pub mod number;
+pub mod operator;
+pub mod queue;
+pub mod reader;
+pub mod registry;
+pub mod rules;
+pub mod stack;
+pub mod token;
+pub mod trace;
+pub mod validator;
+pub mod value;
+pub mod writer;
+
+pub use crate::ast::Expr;
+pub use crate::builder::CalculatorBuilder;
+pub use crate::calculator::Calculator;
+pub use crate::error::CalcError;
+pub use crate::number::{
+    ComplexBackend, DecimalBackend, FloatBackend, InexactPolicy, IntegerBackend, Number, NumberFormat, NumericBackend,
+    RationalBackend,
+};
+pub use crate::value::Value;
+
+use crate::converters::InfixToAst;
+use crate::lexer::RegexpLexer;
+use crate::rules::standard_validator;
+use crate::writer::SilentOutput;
+
+// Количество значащих цифр, до которого по-умолчанию округляются результаты вычислений
+pub const DEFAULT_PRECISION: u64 = 28;
+
+// Глубина вложенных вызовов пользовательских функций, допустимая по-умолчанию
+pub const DEFAULT_RECURSION_LIMIT: usize = 100;
+
+///
+/// Строитель калькулятора со стандартным набором объектов: разбор на токены регулярными выражениями,
+///   стандартные правила валидации, преобразование в дерево выражения и точные десятичные числа
+/// Ввод и вывод не устанавливаются и задаются при необходимости
+///
+pub fn default_builder() -> CalculatorBuilder {
+    let mut builder = CalculatorBuilder::new();
+    builder
+        .lexer(Rc::new(RegexpLexer::new()))
+        .validator(Rc::new(standard_validator()))
+        .converter(Rc::new(InfixToAst::new()))
+        .backend(Rc::new(DecimalBackend::new(DEFAULT_PRECISION)))
+        .output_stream(Rc::new(SilentOutput {}));
+
+    builder
+}
+
+///
+/// Вычисление выражения
+/// Для выражения вида `имя = выражение` возвращается значение правой части
+///
+pub fn evaluate(input: &str) -> Result<Value, CalcError> {
+    let mut calc = default_builder().build("")?;
+    calc.evaluate_line(input).map(|(_, value)| value)
+}
+
+///
+/// Разбор выражения в дерево без вычисления
+/// Для выражения вида `имя = выражение` возвращается дерево правой части
+///
+pub fn parse(input: &str) -> Result<Expr, CalcError> {
+    default_builder().build("")?.parse_line(input)
+}
+
+///
+/// Преобразование выражения в запись обратной польской нотации без вычисления
+///
+pub fn to_rpn(input: &str) -> Result<String, CalcError> {
+    Ok(parse(input)?.to_rpn())
+}
+
+///
+/// Символьная производная выражения по заданной переменной
+/// Результат упрощается и может быть напечатан в инфиксной форме и в обратной польской нотации
+///
+pub fn derivative(input: &str, var: &str) -> Result<Expr, CalcError> {
+    derivative::differentiate(&parse(input)?, var)
+}
+
+// Базовые тесты
+#[cfg(test)]
+#[test]
+fn test_public_api() {
+    assert_eq!(evaluate("2 + 2*2").unwrap().to_string(), "6");
+    assert_eq!(evaluate("x = max(1, 2)").unwrap().to_string(), "2");
+    assert_eq!(evaluate("1 + 2 < 1 << 2 && !false").unwrap(), Value::Bool(true));
+    assert_eq!(to_rpn("(1 + 2) * 3").unwrap(), "1 2 + 3 ×");
+    assert_eq!(parse("-(1 - 2)").unwrap().to_string(), "-(1 - 2)");
+    assert_eq!(derivative("x^2*sin(x)", "x").unwrap().to_rpn(), "2 x × x sin × x 2 pow x cos × +");
+}
+
+#[test]
+fn test_public_api_errors() {
+    assert_eq!(evaluate("1/0").err().unwrap().span(), Some(token::Span::new(1, 2)));
+    assert!(matches!(parse("1 +"), Err(CalcError::Conversion(..))));
+    assert!(matches!(to_rpn("1 $ 2"), Err(CalcError::Lexical(..))));
+    assert_eq!(parse("1 + 2(3)").err().unwrap().span(), Some(token::Span::new(5, 6)));
+    assert_eq!(parse("(1)(2)").err().unwrap().span(), Some(token::Span::new(3, 4)));
+
+    // глубина дерева выражения не ограничена стеком потока
+    assert_eq!(evaluate(&format!("1{}", "+1".repeat(20_000))).unwrap().to_string(), "20001");
+    assert_eq!(evaluate(&format!("{}1", "-".repeat(20_000))).unwrap().to_string(), "1");
+    assert!(evaluate(&format!("{}1{}", "(1+".repeat(400), ")".repeat(400))).is_ok());
+}
@@ -1,26 +1,20 @@
+use std::env;
 use std::io;
+use std::io::IsTerminal;
+use std::process;
 use std::rc::Rc;
 
-mod builder;
-mod calculator;
-mod converters;
-mod lexer;
-mod operator;
-mod queue;
-mod reader;
-mod stack;
-mod token;
-mod validator;
-mod writer;
-
-use crate::builder::CalculatorBuilder;
-use crate::converters::InfixToRPN;
-use crate::lexer::RegexpLexer;
-use crate::reader::ConsoleReader;
-use crate::token::{Token, TokenList, TokenType};
-use crate::validator::Validator;
-
-pub type Rule = fn(&TokenList) -> bool;
+mod cli;
+
+use less_3_task::reader::{BatchReader, ConsoleReader, Reader};
+use less_3_task::rules::{implicit_multiplication_validator, standard_validator};
+use less_3_task::writer::{ConsoleOutput, JsonOutput, PlainOutput, ProgrammerOutput, Writer};
+use less_3_task::{
+    default_builder, Calculator, CalculatorBuilder, ComplexBackend, DecimalBackend, FloatBackend, InexactPolicy,
+    IntegerBackend, NumberFormat, NumericBackend, RationalBackend,
+};
+
+use crate::cli::{parse_args, NumericMode, Options, OutputFormat, USAGE};
 
 ///
 /// Вывод приветственного сообщения на стандартный вывод
@@ -32,11 +26,48 @@ fn print_help() {
   унарные:
     '+'
     '-'
+    '~'
+    '√' - квадратный корень: √2, √(x+1)
+    '!' - логическое отрицание
+  постфиксные:
+    '!'  - факториал: 5!, 0.5! (гамма-функция для дробных)
+    '!!' - двойной факториал: 7!!
+    '%'  - процент: 10% = 0.1, 200 + 10% = 220
   бинарные:
     '+'
     '-'
     '/'
     '*'
+    '%'
+    '^'
+    '<<'
+    '>>'
+    '&', '|', 'xor' - битовые И, ИЛИ, исключающее ИЛИ
+    'mod' - остаток со знаком делителя
+    '==', '!=', '<', '<=', '>', '>=' - сравнения
+    '&&', '||' - логические И, ИЛИ над значениями true, false
+    'c ? a : b', if(c, a, b) - условное выражение, вычисляется только выбранная ветвь
+  символы Unicode: × · ÷ − π и надстрочные показатели степени (x², 2⁻¹)
+  функции:
+    sin, cos, tan, asin, acos, atan, sqrt, cbrt, exp, ln, log10, log2,
+    abs, floor, ceil, round, sign, arg, conj, re, im
+    pow(x, y), atan2(y, x), log(x, base), hypot(x, y)
+    min, max, sum, avg, median - от одного и более аргументов
+  константы:
+    pi, e, tau, phi, c, g, h, k_B, N_A
+  переменные:
+    x = 3*4  - присваивание значения переменной
+    ans      - результат последнего вычисления
+  пользовательские функции:
+    f(x, y) = x^2 + y  - определение (новое определение заменяет прежнее)
+    f(3, 1)            - вызов, допускается рекурсия: fact(n) = n <= 1 ? 1 : n * fact(n - 1)
+    :functions         - список определенных функций
+  производные:
+    d/dx(x^2*sin(x))   - символьная производная по переменной x без вычисления
+Для пошагового вывода работы алгоритма запустите программу с ключом --trace
+Неявное умножение (2(3+4), 2pi, 3sin(x)) включается ключом --implicit
+Для пакетной обработки передайте имя файла с выражениями, либо перенаправьте их на стандартный ввод
+Описание всех ключей командной строки выводится по ключу --help
 Для выхода нажмите <Ctrl+C>"#
     );
 }
@@ -61,86 +92,128 @@ fn request_to_continue() -> bool {
     false
 }
 
-
 ///
-/// Вспомогательные функции передаваемые объекту валидатору для проверки введенных токенов
-/// При первом разборе на токены все операторы сложения и вычитания по-умолчанию считаются
-///  унарными. После успешного разбора на токены проходим по их списку с целью поиска реальных
-///  унарных операторов и заменяем на бинарные где это необходимо.
-/// Бинарность определяется по наличию перед проверяемым токеном закрывающей скобки или числа
+/// Конструирование калькулятора в соответствии с настройками запуска
 ///
-fn check_for_binary_operator(list: &mut TokenList) -> bool {
-    let mut ind: usize = 0;
-    let list_size: usize = list.len();
-
-    while ind < list_size {
-        let tok: &Token = list.get(ind).unwrap();
-        if tok.0 == TokenType::UnaryOperator {
-            if ind > 0 {
-                let prev: &Token = list.get(ind - 1).unwrap();
-                if prev.0 == TokenType::NumberInt
-                    || prev.0 == TokenType::NumberFloat
-                    || prev.0 == TokenType::ClosedParenthesis
-                {
-                    list.get_mut(ind).unwrap().0 = TokenType::BinaryOperator;
-                }
-            }
-        }
-        ind += 1;
+fn configure(options: &Options) -> CalculatorBuilder {
+    let mut writer: Rc<dyn Writer> = match options.output {
+        OutputFormat::Text => Rc::new(ConsoleOutput::default()),
+        OutputFormat::Plain => Rc::new(PlainOutput {}),
+        OutputFormat::Json => Rc::new(JsonOutput {}),
+    };
+    if options.programmer {
+        writer = Rc::new(ProgrammerOutput::new(writer, options.word, !options.unsigned));
     }
 
-    true
+    let backend: Rc<dyn NumericBackend> = match options.mode {
+        NumericMode::Decimal => Rc::new(DecimalBackend::new(options.precision)),
+        NumericMode::Float => Rc::new(FloatBackend {}),
+        NumericMode::Rational if options.strict => Rc::new(RationalBackend::new(InexactPolicy::Error)),
+        NumericMode::Rational => Rc::new(RationalBackend::new(InexactPolicy::Float)),
+        NumericMode::Complex => Rc::new(ComplexBackend {}),
+        NumericMode::Integer => Rc::new(IntegerBackend {}),
+    };
+
+    let validator = match options.implicit {
+        true => implicit_multiplication_validator(),
+        false => standard_validator(),
+    };
+
+    // к стандартному набору объектов калькулятора добавляем ввод, вывод
+    // и настройки, заданные аргументами командной строки
+    let mut builder = default_builder();
+    builder
+        .input_stream(Rc::new(ConsoleReader {}))
+        .output_stream(writer)
+        .backend(backend)
+        .validator(Rc::new(validator))
+        .number_format(NumberFormat {
+            approximation: options.approximation,
+            polar: options.polar,
+            ..NumberFormat::default()
+        })
+        .angle_unit(options.angle_unit)
+        .recursion_limit(options.recursion_limit)
+        .rpn_only(options.rpn_only)
+        .trace(options.trace);
+
+    builder
 }
 
 ///
-/// Вспомогательные функции передаваемые объекту валидатору для проверки введенных токенов
-/// Проверка на наличие двух следующих подряд бинарных операторов
+/// Сборка калькулятора. При ошибке настройки программа завершается
 ///
-fn check_for_repeate_binary_operator(list: &mut TokenList) -> bool {
-    let mut ind: usize = 0;
-    let list_size: usize = list.len();
-
-    while ind < list_size {
-        let tok: &Token = list.get(ind).unwrap();
-        if tok.0 == TokenType::BinaryOperator {
-            if ind > 0 {
-                let prev: &Token = list.get(ind - 1).unwrap();
-                if prev.0 == TokenType::BinaryOperator {
-                    return false;
-                }
-            }
+fn build(builder: &CalculatorBuilder) -> Calculator {
+    match builder.build("") {
+        Ok(calc) => calc,
+        Err(why) => {
+            eprintln!("{}", why);
+            process::exit(2);
         }
-        ind += 1;
     }
-
-    true
 }
 
-
 ///
 /// Точка входа
 ///
 fn main() {
-    print_help();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(why) => {
+            eprintln!("{}\n\n{}", why, USAGE);
+            process::exit(2);
+        }
+    };
 
-    // Создаем объект валидатора и добавляем требуемые правила-проверки
-    let mut validator = Validator::new();
-    validator
-        .add_rule(check_for_binary_operator)
-        .add_rule(check_for_repeate_binary_operator);
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
 
-    // конструируем объект калькулятора, устанавливая необходимые конкретные
-    // имплементации требуемых для вычисления объектов
-    let mut calc = CalculatorBuilder::new()
-        .input_stream(Rc::new(ConsoleReader {}))
-        .lexer(Rc::new(RegexpLexer::new()))
-        .validator(Rc::from(validator))
-        .converter(Rc::new(InfixToRPN {}))
-        .build("");
+    if options.version {
+        println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        return;
+    }
+
+    // Пакетный режим: выражения берутся из аргументов командной строки, из файла, либо из канала
+    let batch_input: Option<Rc<dyn Reader>> = if !options.expressions.is_empty() {
+        let lines = options.expressions.join("\n");
+        Some(Rc::new(BatchReader::new(Box::new(io::Cursor::new(lines)))))
+    } else if let Some(path) = &options.file {
+        match BatchReader::from_file(path) {
+            Ok(reader) => Some(Rc::new(reader)),
+            Err(why) => {
+                eprintln!("{}", why);
+                process::exit(2);
+            }
+        }
+    } else if !io::stdin().is_terminal() {
+        Some(Rc::new(BatchReader::stdin()))
+    } else {
+        None
+    };
+
+    let mut builder = configure(&options);
+
+    // в пакетном режиме приглашение и запрос на продолжение не выводятся,
+    // а при ошибке в любой из строк программа завершается с ненулевым кодом
+    if let Some(input) = batch_input {
+        let mut calc = build(builder.input_stream(input));
+        if !calc.run_batch() {
+            process::exit(1);
+        }
+        return;
+    }
+
+    print_help();
+    let mut calc = build(&builder);
 
     // основной цикл
     loop {
-        calc.run();
+        if calc.run().is_none() {
+            break;
+        }
 
         match request_to_continue() {
             true => continue,
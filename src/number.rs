@@ -0,0 +1,1220 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::num::NonZeroU64;
+
+use bigdecimal::{BigDecimal, Context, RoundingMode, Signed, ToPrimitive, Zero};
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, One, Pow};
+
+///
+/// Число, над которым выполняются вычисления
+/// Конкретное представление определяется выбранной числовой системой (см. NumericBackend)
+///   - Float    - число с плавающей точкой двойной точности
+///   - Decimal  - точное десятичное число произвольной точности
+///   - Rational - точная несократимая дробь с числителем и знаменателем произвольного размера
+///   - Complex  - комплексное число с компонентами двойной точности
+///   - Integer  - целое число произвольного размера
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Number {
+    Float(f64),
+    Decimal(BigDecimal),
+    Rational(BigRational),
+    Complex(Complex64),
+    Integer(BigInt),
+}
+
+// Наибольшая величина сдвига целого числа произвольного размера, бит
+const MAX_INTEGER_SHIFT: i64 = 1 << 20;
+
+// Наибольший аргумент факториала, вычисляемого точно
+const MAX_FACTORIAL: u64 = 10_000;
+
+// Наибольший размер результата точного возведения в степень, бит
+const MAX_POWER_BITS: u64 = 1 << 20;
+
+// Наибольший по модулю порядок точного десятичного числа. Ограничивает длину записи числа
+//   и размер степени 10 при его приведении к рациональному
+const MAX_DECIMAL_SCALE: u64 = 1 << 16;
+
+// Реализация методов для чисел
+// Операции возвращают Result, поэтому не реализуются через типажи std::ops
+#[allow(clippy::should_implement_trait)]
+impl Number {
+    ///
+    /// Разбор числового литерала
+    /// Литерал всегда разбирается в точное десятичное представление,
+    ///   дальнейшее приведение выполняет числовая система калькулятора
+    /// Литерал с суффиксом `i` (`2i`, `0.5i`, `i`) является мнимым числом
+    /// Допускаются литералы с экспонентой (`1e-9`), без целой части (`.5`), целые литералы в шестнадцатеричной (`0xFF`),
+    ///   двоичной (`0b1010`) и восьмеричной (`0o17`) системах счисления, цифры могут разделяться символом `_`
+    ///
+    pub fn parse(literal: &str) -> Result<Number, String> {
+        let error = || format!("некорректный числовой литерал '{}'", literal);
+        let digits = literal.replace('_', "");
+
+        if let Some(coefficient) = digits.strip_suffix('i') {
+            return match coefficient {
+                "" => Ok(Number::Complex(Complex64::i())),
+                _ => match coefficient.parse::<f64>() {
+                    Ok(value) => Ok(Number::Complex(Complex64::new(0.0, value))),
+                    Err(_) => Err(error()),
+                },
+            };
+        }
+
+        let radix = literal_radix(&digits);
+        if radix != 10 {
+            return match BigInt::parse_bytes(&digits.as_bytes()[2..], radix) {
+                Some(value) => Ok(Number::Decimal(BigDecimal::new(value, 0))),
+                None => Err(error()),
+            };
+        }
+
+        match digits.parse::<BigDecimal>() {
+            Ok(value) if value.fractional_digit_count().unsigned_abs() > MAX_DECIMAL_SCALE => Err(format!(
+                "порядок числового литерала '{}' превышает допустимый ({})",
+                literal, MAX_DECIMAL_SCALE
+            )),
+            Ok(value) => Ok(Number::Decimal(value)),
+            Err(_) => Err(error()),
+        }
+    }
+
+    ///
+    /// Приближенное значение числа в виде f64
+    /// Для комплексного числа с ненулевой мнимой частью результатом является NaN
+    ///
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Float(v) => *v,
+            Number::Decimal(v) => v.to_f64().unwrap_or(f64::NAN),
+            Number::Rational(v) => v.to_f64().unwrap_or(f64::NAN),
+            Number::Integer(v) => v.to_f64().unwrap_or(f64::NAN),
+            Number::Complex(z) if z.im == 0.0 => z.re,
+            Number::Complex(_) => f64::NAN,
+        }
+    }
+
+    ///
+    /// Значение числа в виде комплексного числа
+    ///
+    pub fn to_complex(&self) -> Complex64 {
+        match self {
+            Number::Complex(z) => *z,
+            value => Complex64::new(value.to_f64(), 0.0),
+        }
+    }
+
+    ///
+    /// Приведение числа к десятичному представлению
+    /// Для чисел с плавающей точкой используется кратчайшая десятичная запись
+    ///
+    pub fn to_decimal(&self) -> Result<BigDecimal, String> {
+        match self {
+            Number::Decimal(v) => Ok(v.clone()),
+            Number::Integer(v) => Ok(BigDecimal::new(v.clone(), 0)),
+            Number::Rational(v) => Ok(BigDecimal::new(v.numer().clone(), 0) / BigDecimal::new(v.denom().clone(), 0)),
+            Number::Float(v) if v.is_finite() => Ok(format!("{}", v).parse::<BigDecimal>().unwrap()),
+            Number::Complex(z) if z.im == 0.0 => Number::Float(z.re).to_decimal(),
+            Number::Complex(_) => Err(complex_unsupported(self)),
+            Number::Float(v) => Err(format!("значение {} не представимо десятичным числом", v)),
+        }
+    }
+
+    ///
+    /// Приведение числа к рациональному представлению
+    /// Десятичные числа приводятся точно, для чисел с плавающей точкой используется кратчайшая десятичная запись
+    ///
+    pub fn to_rational(&self) -> Result<BigRational, String> {
+        match self {
+            Number::Rational(v) => Ok(v.clone()),
+            Number::Decimal(v) => Ok(decimal_to_rational(v)),
+            Number::Integer(v) => Ok(BigRational::from_integer(v.clone())),
+            Number::Float(_) | Number::Complex(_) => Ok(decimal_to_rational(&self.to_decimal()?)),
+        }
+    }
+
+    ///
+    /// Приведение числа к целому числу произвольного размера
+    /// Ошибкой является число с дробной частью
+    ///
+    pub fn to_integer(&self) -> Result<BigInt, String> {
+        let error = || format!("значение {} не является целым числом", self);
+        match self {
+            Number::Integer(v) => Ok(v.clone()),
+            Number::Decimal(v) if v.is_integer() => Ok(v.with_scale(0).as_bigint_and_exponent().0),
+            Number::Rational(v) if v.is_integer() => Ok(v.to_integer()),
+            Number::Float(v) if v.fract() == 0.0 => BigInt::from_f64(*v).ok_or_else(error),
+            Number::Complex(z) if z.im == 0.0 => Number::Float(z.re).to_integer(),
+            _ => Err(error()),
+        }
+    }
+
+    pub fn neg(self) -> Result<Number, String> {
+        match self {
+            Number::Float(v) => Ok(Number::Float(-v)),
+            Number::Decimal(v) => Ok(Number::Decimal(-v)),
+            Number::Rational(v) => Ok(Number::Rational(-v)),
+            Number::Complex(z) => Ok(Number::Complex(-z)),
+            Number::Integer(v) => Ok(Number::Integer(-v)),
+        }
+    }
+
+    pub fn add(self, rhs: Number) -> Result<Number, String> {
+        match Number::unify(self, rhs) {
+            (Number::Float(x), Number::Float(y)) => Ok(Number::Float(x + y)),
+            (Number::Decimal(x), Number::Decimal(y)) => Ok(Number::Decimal(x + y)),
+            (Number::Rational(x), Number::Rational(y)) => Ok(Number::Rational(x + y)),
+            (Number::Complex(x), Number::Complex(y)) => Ok(Number::Complex(x + y)),
+            (Number::Integer(x), Number::Integer(y)) => Ok(Number::Integer(x + y)),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn sub(self, rhs: Number) -> Result<Number, String> {
+        match Number::unify(self, rhs) {
+            (Number::Float(x), Number::Float(y)) => Ok(Number::Float(x - y)),
+            (Number::Decimal(x), Number::Decimal(y)) => Ok(Number::Decimal(x - y)),
+            (Number::Rational(x), Number::Rational(y)) => Ok(Number::Rational(x - y)),
+            (Number::Complex(x), Number::Complex(y)) => Ok(Number::Complex(x - y)),
+            (Number::Integer(x), Number::Integer(y)) => Ok(Number::Integer(x - y)),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn mul(self, rhs: Number) -> Result<Number, String> {
+        match Number::unify(self, rhs) {
+            (Number::Float(x), Number::Float(y)) => Ok(Number::Float(x * y)),
+            (Number::Decimal(x), Number::Decimal(y)) => Number::checked_decimal(x * y),
+            (Number::Rational(x), Number::Rational(y)) => Ok(Number::Rational(x * y)),
+            (Number::Complex(x), Number::Complex(y)) => Ok(Number::Complex(x * y)),
+            (Number::Integer(x), Number::Integer(y)) => Ok(Number::Integer(x * y)),
+            _ => unreachable!(),
+        }
+    }
+
+    ///
+    /// Деление. Для чисел с плавающей точкой деление на ноль дает бесконечность,
+    ///   для точных чисел - ошибку
+    /// Целые числа делятся нацело с отбрасыванием дробной части (как в языке С): 7/2 = 3, -7/2 = -3
+    ///
+    pub fn div(self, rhs: Number) -> Result<Number, String> {
+        match Number::unify(self, rhs) {
+            (Number::Float(x), Number::Float(y)) => Ok(Number::Float(x / y)),
+            (Number::Decimal(_), Number::Decimal(y)) if y.is_zero() => {
+                Err("деление на ноль".to_string())
+            }
+            (Number::Decimal(x), Number::Decimal(y)) => Number::checked_decimal(x / y),
+            (Number::Rational(_), Number::Rational(y)) if y.is_zero() => {
+                Err("деление на ноль".to_string())
+            }
+            (Number::Rational(x), Number::Rational(y)) => Ok(Number::Rational(x / y)),
+            (Number::Complex(_), Number::Complex(y)) if y.is_zero() => {
+                Err("деление на ноль".to_string())
+            }
+            (Number::Complex(x), Number::Complex(y)) => Ok(Number::Complex(x / y)),
+            (Number::Integer(_), Number::Integer(y)) if y.is_zero() => {
+                Err("деление на ноль".to_string())
+            }
+            (Number::Integer(x), Number::Integer(y)) => Ok(Number::Integer(x / y)),
+            _ => unreachable!(),
+        }
+    }
+
+    ///
+    /// Остаток от деления. Знак остатка совпадает со знаком делимого (как в языке С): -7 % 3 = -1
+    ///
+    pub fn rem(self, rhs: Number) -> Result<Number, String> {
+        match Number::unify(self, rhs) {
+            (Number::Float(x), Number::Float(y)) => Ok(Number::Float(x % y)),
+            (Number::Decimal(_), Number::Decimal(y)) if y.is_zero() => {
+                Err("деление на ноль".to_string())
+            }
+            (Number::Decimal(x), Number::Decimal(y)) => Ok(Number::Decimal(x % y)),
+            (Number::Rational(_), Number::Rational(y)) if y.is_zero() => {
+                Err("деление на ноль".to_string())
+            }
+            (Number::Rational(x), Number::Rational(y)) => Ok(Number::Rational(x % y)),
+            (Number::Complex(x), Number::Complex(y)) if x.im == 0.0 && y.im == 0.0 => {
+                Number::Float(x.re).rem(Number::Float(y.re))?.into_kind_of(&Number::Complex(x))
+            }
+            (Number::Complex(_), Number::Complex(_)) => {
+                Err("остаток от деления не определен для комплексных чисел".to_string())
+            }
+            (Number::Integer(_), Number::Integer(y)) if y.is_zero() => {
+                Err("деление на ноль".to_string())
+            }
+            (Number::Integer(x), Number::Integer(y)) => Ok(Number::Integer(x % y)),
+            _ => unreachable!(),
+        }
+    }
+
+    ///
+    /// Остаток от деления с округлением частного вниз. Знак остатка совпадает со знаком делителя: -7 mod 3 = 2
+    ///
+    pub fn modulo(self, rhs: Number) -> Result<Number, String> {
+        let zero = Number::Decimal(BigDecimal::zero());
+        let remainder = self.rem(rhs.clone())?;
+        let sign = remainder.compare(&zero);
+        if sign != Some(Ordering::Equal) && sign != rhs.compare(&zero) {
+            return remainder.add(rhs);
+        }
+
+        Ok(remainder)
+    }
+
+    ///
+    /// Возведение в степень. Для десятичных чисел целая степень вычисляется точно,
+    ///   дробная - через f64
+    /// Для рациональных чисел точно вычисляется также дробная степень p/q, если из основания
+    ///   извлекается точный корень степени q. Иначе результат является числом с плавающей точкой
+    /// Для комплексных чисел вычисляется главное значение степени
+    /// Целое число возводится только в неотрицательную целую степень
+    /// Размер точного результата ограничен MAX_POWER_BITS бит, большая степень является ошибкой
+    ///
+    pub fn pow(self, rhs: Number) -> Result<Number, String> {
+        match Number::unify(self, rhs) {
+            (Number::Float(x), Number::Float(y)) => Ok(Number::Float(x.powf(y))),
+            (Number::Decimal(x), Number::Decimal(y)) => {
+                if y.is_integer() {
+                    if let Some(exp) = y.to_i64() {
+                        if x.is_zero() && exp < 0 {
+                            return Err("деление на ноль".to_string());
+                        }
+                        if exp == 0 {
+                            return Ok(Number::Decimal(BigDecimal::from(1)));
+                        }
+                        // степень ±1 не растет, поэтому достаточно четности показателя
+                        if x.abs().is_one() {
+                            return Ok(Number::Decimal(x.powi(exp % 2)));
+                        }
+                        if !x.is_zero() {
+                            Number::power_size(x.as_bigint_and_exponent().0.bits(), exp)?;
+                        }
+                        return Number::checked_decimal(x.powi(exp));
+                    }
+                }
+
+                Number::Float(x.to_f64().unwrap_or(f64::NAN).powf(y.to_f64().unwrap_or(f64::NAN)))
+                    .into_kind_of(&Number::Decimal(x))
+            }
+            (Number::Rational(x), Number::Rational(y)) => {
+                if let (Some(exp), Some(degree)) = (y.numer().to_i32(), y.denom().to_u32()) {
+                    if let Some(base) = exact_root(&x, degree) {
+                        if base.is_zero() && exp < 0 {
+                            return Err("деление на ноль".to_string());
+                        }
+                        if base.numer().bits() > 1 || base.denom().bits() > 1 {
+                            Number::power_size(base.numer().bits() + base.denom().bits(), exp as i64)?;
+                        }
+                        return Ok(Number::Rational(Pow::pow(base, exp)));
+                    }
+                }
+
+                Ok(Number::Float(x.to_f64().unwrap_or(f64::NAN).powf(y.to_f64().unwrap_or(f64::NAN))))
+            }
+            (Number::Complex(x), Number::Complex(y)) => {
+                // нулевая степень, в том числе нуля, равна 1, как и для остальных представлений
+                if y.is_zero() {
+                    return Ok(Number::Complex(Complex64::new(1.0, 0.0)));
+                }
+                if x.is_zero() {
+                    return match y.re.partial_cmp(&0.0) {
+                        Some(Ordering::Greater) if y.im == 0.0 => Ok(Number::Complex(x)),
+                        _ => Err("деление на ноль".to_string()),
+                    };
+                }
+                // целая степень вычисляется умножением, чтобы, например, i^2 было ровно -1
+                if y.im == 0.0 && y.re.fract() == 0.0 && y.re.abs() <= i32::MAX as f64 {
+                    return Ok(Number::Complex(x.powi(y.re as i32)));
+                }
+                Ok(Number::Complex(x.powc(y)))
+            }
+            (Number::Integer(x), Number::Integer(y)) => match y.to_u32() {
+                Some(exp) => {
+                    if x.bits() > 1 {
+                        Number::power_size(x.bits(), exp as i64)?;
+                    }
+                    Ok(Number::Integer(Pow::pow(x, exp)))
+                }
+                None if y.is_negative() => Err(format!("отрицательная степень {} не дает целого результата", y)),
+                None => Err(format!("слишком большая степень {}", y)),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    ///
+    /// Битовые сдвиги. Аргументы приводятся к целым 64-битным числам,
+    ///   целые числа произвольного размера сдвигаются без потери разрядов
+    ///
+    pub fn shl(self, rhs: Number) -> Result<Number, String> {
+        if let Number::Integer(x) = self {
+            return Ok(Number::Integer(x << Number::integer_shift_amount(&rhs)?));
+        }
+
+        let (x, y) = (self.to_i64()?, rhs.to_i64()?);
+        let shift = Number::shift_amount(y)?;
+        Number::Decimal(BigDecimal::from(x.wrapping_shl(shift))).into_kind_of(&self)
+    }
+
+    pub fn shr(self, rhs: Number) -> Result<Number, String> {
+        if let Number::Integer(x) = self {
+            return Ok(Number::Integer(x >> Number::integer_shift_amount(&rhs)?));
+        }
+
+        let (x, y) = (self.to_i64()?, rhs.to_i64()?);
+        let shift = Number::shift_amount(y)?;
+        Number::Decimal(BigDecimal::from(x.wrapping_shr(shift))).into_kind_of(&self)
+    }
+
+    ///
+    /// Битовые операции И, ИЛИ, исключающее ИЛИ. Аргументы, не являющиеся целыми числами произвольного размера,
+    ///   приводятся к целым 64-битным числам
+    ///
+    pub fn bitand(self, rhs: Number) -> Result<Number, String> {
+        self.bitwise(rhs, |x, y| x & y, |x, y| x & y)
+    }
+
+    pub fn bitor(self, rhs: Number) -> Result<Number, String> {
+        self.bitwise(rhs, |x, y| x | y, |x, y| x | y)
+    }
+
+    pub fn bitxor(self, rhs: Number) -> Result<Number, String> {
+        self.bitwise(rhs, |x, y| x ^ y, |x, y| x ^ y)
+    }
+
+    ///
+    /// Битовое отрицание в дополнительном коде: ~x = -x - 1
+    ///
+    pub fn not(self) -> Result<Number, String> {
+        match self {
+            Number::Integer(v) => Ok(Number::Integer(!v)),
+            value => Number::Decimal(BigDecimal::from(!value.to_i64()?)).into_kind_of(&value),
+        }
+    }
+
+    pub fn abs(self) -> Result<Number, String> {
+        match self {
+            Number::Float(v) => Ok(Number::Float(v.abs())),
+            Number::Decimal(v) => Ok(Number::Decimal(v.abs())),
+            Number::Rational(v) => Ok(Number::Rational(v.abs())),
+            Number::Complex(z) => Ok(Number::Complex(Complex64::new(z.norm(), 0.0))),
+            Number::Integer(v) => Ok(Number::Integer(v.abs())),
+        }
+    }
+
+    pub fn floor(self) -> Result<Number, String> {
+        match self {
+            Number::Float(v) => Ok(Number::Float(v.floor())),
+            Number::Decimal(v) => Ok(Number::Decimal(v.with_scale_round(0, RoundingMode::Floor))),
+            Number::Rational(v) => Ok(Number::Rational(v.floor())),
+            Number::Complex(z) => Ok(Number::Complex(Complex64::new(z.re.floor(), z.im.floor()))),
+            Number::Integer(v) => Ok(Number::Integer(v)),
+        }
+    }
+
+    pub fn ceil(self) -> Result<Number, String> {
+        match self {
+            Number::Float(v) => Ok(Number::Float(v.ceil())),
+            Number::Decimal(v) => Ok(Number::Decimal(v.with_scale_round(0, RoundingMode::Ceiling))),
+            Number::Rational(v) => Ok(Number::Rational(v.ceil())),
+            Number::Complex(z) => Ok(Number::Complex(Complex64::new(z.re.ceil(), z.im.ceil()))),
+            Number::Integer(v) => Ok(Number::Integer(v)),
+        }
+    }
+
+    ///
+    /// Округление до ближайшего целого, половина округляется от нуля
+    ///
+    pub fn round(self) -> Result<Number, String> {
+        match self {
+            Number::Float(v) => Ok(Number::Float(v.round())),
+            Number::Decimal(v) => Ok(Number::Decimal(v.with_scale_round(0, RoundingMode::HalfUp))),
+            Number::Rational(v) => Ok(Number::Rational(v.round())),
+            Number::Complex(z) => Ok(Number::Complex(Complex64::new(z.re.round(), z.im.round()))),
+            Number::Integer(v) => Ok(Number::Integer(v)),
+        }
+    }
+
+    ///
+    /// Знак числа: -1, 0 или 1. Для комплексного числа - число с единичным модулем и тем же аргументом
+    ///
+    pub fn sign(self) -> Result<Number, String> {
+        match self {
+            Number::Float(v) if v == 0.0 || v.is_nan() => Ok(Number::Float(v)),
+            Number::Float(v) => Ok(Number::Float(v.signum())),
+            Number::Decimal(v) => Ok(Number::Decimal(v.signum())),
+            Number::Rational(v) => Ok(Number::Rational(v.signum())),
+            Number::Complex(z) if z.is_zero() => Ok(Number::Complex(z)),
+            Number::Complex(z) => Ok(Number::Complex(z / z.norm())),
+            Number::Integer(v) => Ok(Number::Integer(v.signum())),
+        }
+    }
+
+    pub fn sqrt(self) -> Result<Number, String> {
+        match self {
+            Number::Decimal(v) if !v.is_negative() => Ok(Number::Decimal(v.sqrt().unwrap())),
+            Number::Rational(v) if exact_root(&v, 2).is_some() => Ok(Number::Rational(exact_root(&v, 2).unwrap())),
+            Number::Complex(z) => Ok(Number::Complex(z.sqrt())),
+            Number::Integer(v) if !v.is_negative() && Pow::pow(v.sqrt(), 2u32) == v => Ok(Number::Integer(v.sqrt())),
+            value => value.apply_real("sqrt", f64::sqrt),
+        }
+    }
+
+    ///
+    /// Факториал. Для целых неотрицательных чисел вычисляется точно, для дробных - через гамма-функцию: x! = Г(x + 1)
+    /// Для отрицательных целых чисел факториал не определен
+    ///
+    pub fn factorial(self) -> Result<Number, String> {
+        match self.to_integer() {
+            Ok(n) => Number::Integer(Number::product(&n, 1)?).into_kind_of(&self),
+            Err(_) => self.apply_real("!", |x| gamma(x + 1.0)),
+        }
+    }
+
+    ///
+    /// Двойной факториал: n!! = n·(n-2)·(n-4)·... Определен только для целых неотрицательных чисел
+    ///
+    pub fn double_factorial(self) -> Result<Number, String> {
+        let n = self.to_integer()?;
+        Number::Integer(Number::product(&n, 2)?).into_kind_of(&self)
+    }
+
+    ///
+    /// Процент: доля числа, x% = x/100
+    /// Для целых чисел нецелая доля является ошибкой, а не отбрасывается: 10% = 0.1
+    ///
+    pub fn percent(self) -> Result<Number, String> {
+        let hundred = BigInt::from(100);
+        match self {
+            Number::Integer(v) if !(&v % &hundred).is_zero() => {
+                Err(format!("значение {}% не является целым числом", v))
+            }
+            value => value.div(Number::Integer(hundred)),
+        }
+    }
+
+    ///
+    /// Вычисление вещественной функции через f64
+    /// Результат приводится к представлению аргумента (кроме рационального, см. into_kind_of). Если функция не определена
+    ///   для данного аргумента (результат NaN), то возвращается ошибка
+    ///
+    pub fn apply_real(self, name: &str, f: fn(f64) -> f64) -> Result<Number, String> {
+        if let Number::Complex(z) = self {
+            if z.im != 0.0 {
+                return Err(format!("функция {} не определена для комплексного аргумента {}", name, self));
+            }
+        }
+
+        let arg = self.to_f64();
+        let result = f(arg);
+        if result.is_nan() && !arg.is_nan() {
+            return Err(format!("аргумент {} вне области определения функции {}", self, name));
+        }
+
+        Number::Float(result).into_kind_of(&self)
+    }
+
+    ///
+    /// Вычисление вещественной функции двух аргументов через f64
+    /// Результат приводится к общему представлению аргументов
+    ///
+    pub fn apply_real2(self, rhs: Number, name: &str, f: fn(f64, f64) -> f64) -> Result<Number, String> {
+        let (lhs, rhs) = Number::unify(self, rhs);
+        if let (Number::Complex(x), Number::Complex(y)) = (&lhs, &rhs) {
+            if x.im != 0.0 || y.im != 0.0 {
+                return Err(format!("функция {} не определена для комплексных аргументов ({}, {})", name, lhs, rhs));
+            }
+        }
+
+        let (x, y) = (lhs.to_f64(), rhs.to_f64());
+        let result = f(x, y);
+        if result.is_nan() && !x.is_nan() && !y.is_nan() {
+            return Err(format!("аргументы ({}, {}) вне области определения функции {}", lhs, rhs, name));
+        }
+
+        Number::Float(result).into_kind_of(&lhs)
+    }
+
+    ///
+    /// Вычисление функции, определенной как для вещественных, так и для комплексных аргументов
+    /// Вещественный аргумент вычисляется через f64 (см. apply_real), комплексный - через complex
+    ///
+    pub fn apply_analytic(self, name: &str, real: fn(f64) -> f64, complex: fn(Complex64) -> Complex64) -> Result<Number, String> {
+        match self {
+            Number::Complex(z) => {
+                let result = complex(z);
+                if result.is_nan() && !z.is_nan() {
+                    return Err(format!("аргумент {} вне области определения функции {}", self, name));
+                }
+                Ok(Number::Complex(result))
+            }
+            value => value.apply_real(name, real),
+        }
+    }
+
+    ///
+    /// Аргумент (фаза) числа в радианах
+    ///
+    pub fn arg(self) -> Result<Number, String> {
+        match self {
+            Number::Complex(z) => Ok(Number::Complex(Complex64::new(z.arg(), 0.0))),
+            value => value.apply_real("arg", |x| 0f64.atan2(x)),
+        }
+    }
+
+    ///
+    /// Комплексно сопряженное число. Вещественное число сопряжено самому себе
+    ///
+    pub fn conj(self) -> Result<Number, String> {
+        match self {
+            Number::Complex(z) => Ok(Number::Complex(z.conj())),
+            value => Ok(value),
+        }
+    }
+
+    ///
+    /// Вещественная часть числа
+    ///
+    pub fn re(self) -> Result<Number, String> {
+        match self {
+            Number::Complex(z) => Ok(Number::Complex(Complex64::new(z.re, 0.0))),
+            value => Ok(value),
+        }
+    }
+
+    ///
+    /// Мнимая часть числа. Для вещественного числа - ноль в том же представлении
+    ///
+    pub fn im(self) -> Result<Number, String> {
+        match self {
+            Number::Complex(z) => Ok(Number::Complex(Complex64::new(z.im, 0.0))),
+            value => Number::Decimal(BigDecimal::zero()).into_kind_of(&value),
+        }
+    }
+
+    ///
+    /// Проверка двух чисел на равенство с приведением к общему представлению
+    ///
+    pub fn equals(&self, rhs: &Number) -> bool {
+        match Number::unify(self.clone(), rhs.clone()) {
+            (Number::Complex(x), Number::Complex(y)) => x == y,
+            (x, y) => x.compare(&y) == Some(Ordering::Equal),
+        }
+    }
+
+    ///
+    /// Сравнение двух чисел
+    /// Комплексные числа с ненулевой мнимой частью несравнимы
+    ///
+    pub fn compare(&self, rhs: &Number) -> Option<Ordering> {
+        match Number::unify(self.clone(), rhs.clone()) {
+            (Number::Float(x), Number::Float(y)) => x.partial_cmp(&y),
+            (Number::Decimal(x), Number::Decimal(y)) => x.partial_cmp(&y),
+            (Number::Rational(x), Number::Rational(y)) => x.partial_cmp(&y),
+            (Number::Complex(x), Number::Complex(y)) if x.im == 0.0 && y.im == 0.0 => x.re.partial_cmp(&y.re),
+            (Number::Complex(_), Number::Complex(_)) => None,
+            (Number::Integer(x), Number::Integer(y)) => x.partial_cmp(&y),
+            _ => unreachable!(),
+        }
+    }
+
+    ///
+    /// Приведение значения к тому же представлению, что и образец
+    /// Число с плавающей точкой не приводится к рациональному: неточный результат не может стать точным
+    /// Приведение к целому числу с дробной частью является ошибкой
+    ///
+    pub fn into_kind_of(self, sample: &Number) -> Result<Number, String> {
+        match (sample, self) {
+            (Number::Float(_), value) => Ok(Number::Float(value.to_f64())),
+            (Number::Decimal(_), value) => Ok(Number::Decimal(value.to_decimal()?)),
+            (Number::Rational(_), Number::Float(v)) => Ok(Number::Float(v)),
+            (Number::Rational(_), value) => Ok(Number::Rational(value.to_rational()?)),
+            (Number::Complex(_), value) => Ok(Number::Complex(value.to_complex())),
+            (Number::Integer(_), value) => Ok(Number::Integer(value.to_integer()?)),
+        }
+    }
+
+    // Приведение двух аргументов к общему представлению.
+    // Если хотя бы один из аргументов является комплексным, то оба приводятся к комплексным.
+    // Если хотя бы один из аргументов является числом с плавающей точкой, то результат неточный,
+    //   десятичное число вместе с рациональным приводится к рациональному без потери точности,
+    //   целое число приводится к представлению второго аргумента без потери точности
+    fn unify(lhs: Number, rhs: Number) -> (Number, Number) {
+        match (lhs, rhs) {
+            (Number::Complex(x), y) => (Number::Complex(x), Number::Complex(y.to_complex())),
+            (x, Number::Complex(y)) => (Number::Complex(x.to_complex()), Number::Complex(y)),
+            (Number::Integer(x), Number::Decimal(y)) => (Number::Decimal(BigDecimal::new(x, 0)), Number::Decimal(y)),
+            (Number::Decimal(x), Number::Integer(y)) => (Number::Decimal(x), Number::Decimal(BigDecimal::new(y, 0))),
+            (Number::Integer(x), Number::Rational(y)) => (Number::Rational(BigRational::from_integer(x)), Number::Rational(y)),
+            (Number::Rational(x), Number::Integer(y)) => (Number::Rational(x), Number::Rational(BigRational::from_integer(y))),
+            (Number::Decimal(x), Number::Rational(y)) => (Number::Rational(decimal_to_rational(&x)), Number::Rational(y)),
+            (Number::Rational(x), Number::Decimal(y)) => (Number::Rational(x), Number::Rational(decimal_to_rational(&y))),
+            (Number::Float(x), Number::Float(y)) => (Number::Float(x), Number::Float(y)),
+            (Number::Float(x), y) => (Number::Float(x), Number::Float(y.to_f64())),
+            (x, Number::Float(y)) => (Number::Float(x.to_f64()), Number::Float(y)),
+            pair => pair,
+        }
+    }
+
+    fn to_i64(&self) -> Result<i64, String> {
+        match self {
+            Number::Float(v) if v.is_finite() => Ok(v.trunc() as i64),
+            Number::Complex(z) if z.im == 0.0 && z.re.is_finite() => Ok(z.re.trunc() as i64),
+            Number::Decimal(v) => v.with_scale(0).to_i64().ok_or_else(|| format!("значение {} слишком велико для битовой операции", v)),
+            Number::Rational(v) => v.to_integer().to_i64().ok_or_else(|| format!("значение {} слишком велико для битовой операции", v)),
+            Number::Integer(v) => v.to_i64().ok_or_else(|| format!("значение {} слишком велико для битовой операции", v)),
+            _ => Err(format!("значение {} не является целым числом", self)),
+        }
+    }
+
+    // Проверка размера результата точного возведения в степень: разрядность основания, умноженная на показатель,
+    //   не должна превышать MAX_POWER_BITS
+    fn power_size(bits: u64, exp: i64) -> Result<(), String> {
+        if bits.saturating_mul(exp.unsigned_abs()) > MAX_POWER_BITS {
+            return Err(format!("слишком большая степень {}", exp));
+        }
+        Ok(())
+    }
+
+    // Проверка порядка точного десятичного результата: при умножении, делении и возведении в степень
+    //   порядок растет, и его величина не должна превышать MAX_DECIMAL_SCALE
+    fn checked_decimal(v: BigDecimal) -> Result<Number, String> {
+        if v.fractional_digit_count().unsigned_abs() > MAX_DECIMAL_SCALE {
+            return Err(format!("порядок результата превышает допустимый ({})", MAX_DECIMAL_SCALE));
+        }
+        Ok(Number::Decimal(v))
+    }
+
+    // Величина сдвига целого числа произвольного размера
+    fn integer_shift_amount(rhs: &Number) -> Result<usize, String> {
+        let y = rhs.to_i64()?;
+        if (0..=MAX_INTEGER_SHIFT).contains(&y) {
+            Ok(y as usize)
+        } else {
+            Err(format!("недопустимая величина сдвига {}", y))
+        }
+    }
+
+    // Битовая операция над целыми числами произвольного размера (big), либо над целыми 64-битными числами (small)
+    // Результат 64-битной операции приводится к представлению левого аргумента
+    fn bitwise(self, rhs: Number, big: fn(BigInt, BigInt) -> BigInt, small: fn(i64, i64) -> i64) -> Result<Number, String> {
+        match (self, rhs) {
+            (Number::Integer(x), Number::Integer(y)) => Ok(Number::Integer(big(x, y))),
+            (x, y) => Number::Decimal(BigDecimal::from(small(x.to_i64()?, y.to_i64()?))).into_kind_of(&x),
+        }
+    }
+
+    // Произведение n·(n-step)·(n-2·step)·... положительных чисел, не превосходящих n. Для n = 0 равно 1
+    fn product(n: &BigInt, step: usize) -> Result<BigInt, String> {
+        if n.is_negative() {
+            return Err(format!("факториал отрицательного числа {} не определен", n));
+        }
+        match n.to_u64() {
+            Some(n) if n <= MAX_FACTORIAL => Ok((1..=n).rev().step_by(step).map(BigInt::from).product()),
+            _ => Err(format!("слишком большой аргумент факториала {}", n)),
+        }
+    }
+
+    fn shift_amount(y: i64) -> Result<u32, String> {
+        if (0..64).contains(&y) {
+            Ok(y as u32)
+        } else {
+            Err(format!("недопустимая величина сдвига {}", y))
+        }
+    }
+}
+
+// Вывод числа в текстовом виде
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Float(v) => write!(f, "{}", v),
+            Number::Decimal(v) => write!(f, "{}", v.normalized().to_plain_string()),
+            Number::Rational(v) => write!(f, "{}", v),
+            Number::Complex(z) => write!(f, "{}", format_complex(z)),
+            Number::Integer(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+// Запись комплексного числа в алгебраической форме a+bi. Нулевые части не печатаются, единичный коэффициент
+//   мнимой части опускается: 3, 2i, -i, 1-2.5i
+fn format_complex(z: &Complex64) -> String {
+    if z.im == 0.0 {
+        return format!("{}", z.re + 0.0);
+    }
+
+    let sign = if z.im < 0.0 { "-" } else { "+" };
+    let imaginary = if z.im.abs() == 1.0 {
+        "i".to_string()
+    } else {
+        format!("{}i", z.im.abs())
+    };
+
+    if z.re != 0.0 {
+        format!("{}{}{}", z.re, sign, imaginary)
+    } else if z.im < 0.0 {
+        format!("-{}", imaginary)
+    } else {
+        imaginary
+    }
+}
+
+///
+/// Основание системы счисления числового литерала по его префиксу: 0x - 16, 0b - 2, 0o - 8, иначе 10
+///
+pub fn literal_radix(literal: &str) -> u32 {
+    match literal.get(..2) {
+        Some("0x") | Some("0X") => 16,
+        Some("0b") | Some("0B") => 2,
+        Some("0o") | Some("0O") => 8,
+        _ => 10,
+    }
+}
+
+///
+/// Запись целого числа в заданной системе счисления с префиксом (0x, 0b, 0o): -255 => -0xFF
+///
+pub fn to_radix_string(value: &BigInt, radix: u32) -> String {
+    let prefix = match radix {
+        16 => "0x",
+        2 => "0b",
+        8 => "0o",
+        _ => "",
+    };
+    let sign = if value.is_negative() { "-" } else { "" };
+
+    format!("{}{}{}", sign, prefix, value.magnitude().to_str_radix(radix).to_uppercase())
+}
+
+// Гамма-функция вещественного аргумента: аппроксимация Ланцоша (g = 7, 9 коэффициентов),
+//   для x < 0.5 используется формула отражения Г(x)·Г(1 - x) = π / sin(πx)
+fn gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        return std::f64::consts::PI / ((std::f64::consts::PI * x).sin() * gamma(1.0 - x));
+    }
+
+    let x = x - 1.0;
+    let t = x + G + 0.5;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (ind, c)| sum + c / (x + ind as f64 + 1.0));
+    (2.0 * std::f64::consts::PI).sqrt() * t.powf(x + 0.5) * (-t).exp() * sum
+}
+
+// Описание ошибки использования комплексного числа в вещественной числовой системе
+fn complex_unsupported(value: &Number) -> String {
+    format!("значение {} является комплексным числом, комплексные числа доступны только в комплексном режиме", value)
+}
+
+// Точное преобразование десятичного числа в рациональное
+// Порядок десятичного числа не превышает MAX_DECIMAL_SCALE, поэтому степень 10 ограничена по размеру
+fn decimal_to_rational(v: &BigDecimal) -> BigRational {
+    let (digits, scale) = v.as_bigint_and_exponent();
+    let power = Pow::pow(BigInt::from(10), scale.unsigned_abs());
+    if scale >= 0 {
+        BigRational::new(digits, power)
+    } else {
+        BigRational::from_integer(digits * power)
+    }
+}
+
+// Точный корень степени n из рационального числа
+// Возвращает None, если числитель или знаменатель не являются точными степенями
+fn exact_root(v: &BigRational, n: u32) -> Option<BigRational> {
+    if n == 0 || (n.is_multiple_of(2) && v.is_negative()) {
+        return None;
+    }
+
+    let root = |x: &BigInt| {
+        let r = x.nth_root(n);
+        if Pow::pow(&r, n) == *x {
+            Some(r)
+        } else {
+            None
+        }
+    };
+
+    Some(BigRational::new(root(v.numer())?, root(v.denom())?))
+}
+
+///
+/// Настройки представления результата вычисления
+///   - approximation - количество значащих цифр десятичного приближения, выводимого после дроби
+///   - polar         - вывод комплексных чисел в полярной форме: модуль∠аргумент (аргумент в радианах)
+///   - radix         - система счисления, в которой выводятся целые результаты (2, 8, 16), по-умолчанию десятичная
+///
+#[derive(Debug, Clone, Default)]
+pub struct NumberFormat {
+    pub approximation: Option<u64>,
+    pub polar: bool,
+    pub radix: Option<u32>,
+}
+
+// Реализация методов представления результата
+impl NumberFormat {
+    ///
+    /// Текстовое представление числа. Дробь, если задано, дополняется десятичным приближением: 1/3 ≈ 0.3333,
+    ///   комплексное число выводится в алгебраической (1+i) или полярной (1.4142135623730951∠0.7853981633974483) форме,
+    ///   целое число - в заданной системе счисления
+    ///
+    pub fn format(&self, value: &Number) -> String {
+        match (value, self.approximation) {
+            (Number::Rational(v), Some(digits)) if !v.is_integer() => {
+                let decimal = DecimalBackend::new(digits).normalize(value.clone());
+                match decimal {
+                    Ok(decimal) => format!("{} ≈ {}", value, decimal),
+                    Err(_) => value.to_string(),
+                }
+            }
+            (Number::Complex(z), _) if self.polar => format!("{}∠{}", z.norm(), z.arg()),
+            _ => match (self.radix, value.to_integer()) {
+                (Some(radix), Ok(v)) if radix != 10 => to_radix_string(&v, radix),
+                _ => value.to_string(),
+            },
+        }
+    }
+}
+
+///
+/// Типаж для определения числовой системы калькулятора
+/// Приводит литералы и результаты всех операций к своему представлению чисел
+///
+pub trait NumericBackend {
+    fn normalize(&self, value: Number) -> Result<Number, String>;
+
+    ///
+    /// Поддерживает ли числовая система комплексные числа
+    /// Только в такой системе имя `i` является мнимой единицей, в остальных это имя переменной
+    ///
+    fn is_complex(&self) -> bool {
+        false
+    }
+}
+
+///
+/// Числовая система на основе чисел с плавающей точкой (f64)
+///
+pub struct FloatBackend;
+
+impl NumericBackend for FloatBackend {
+    fn normalize(&self, value: Number) -> Result<Number, String> {
+        match value {
+            Number::Complex(z) if z.im != 0.0 => Err(complex_unsupported(&value)),
+            value => Ok(Number::Float(value.to_f64())),
+        }
+    }
+}
+
+///
+/// Числовая система на основе точных десятичных чисел
+/// Результат каждой операции округляется до заданного количества значащих цифр
+///   по правилу банковского округления.
+/// Промежуточная точность деления ограничена 100 значащими цифрами
+///
+pub struct DecimalBackend {
+    context: Context,
+}
+
+// Реализация методов десятичной числовой системы
+impl DecimalBackend {
+    ///
+    /// Создание числовой системы с заданным количеством значащих цифр
+    ///
+    pub fn new(precision: u64) -> Self {
+        let precision = NonZeroU64::new(precision).unwrap_or_else(|| NonZeroU64::new(1).unwrap());
+        DecimalBackend {
+            context: Context::new(precision, RoundingMode::HalfEven),
+        }
+    }
+}
+
+impl NumericBackend for DecimalBackend {
+    fn normalize(&self, value: Number) -> Result<Number, String> {
+        let value = value.to_decimal()?;
+        if value.digits() > self.context.precision().get() {
+            return Ok(Number::Decimal(self.context.round_decimal(value)));
+        }
+
+        Ok(Number::Decimal(value))
+    }
+}
+
+///
+/// Поведение точной числовой системы при операциях, результат которых не является рациональным числом
+///   (корни, тригонометрические функции, логарифмы)
+///   - Float - вычисление продолжается в числах с плавающей точкой
+///   - Error - вычисление завершается ошибкой
+///
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InexactPolicy {
+    Float,
+    Error,
+}
+
+///
+/// Числовая система на основе рациональных чисел (несократимых дробей произвольного размера)
+/// Сложение, вычитание, умножение, деление и возведение в целую степень выполняются точно
+///
+pub struct RationalBackend {
+    policy: InexactPolicy,
+}
+
+// Реализация методов рациональной числовой системы
+impl RationalBackend {
+    ///
+    /// Создание числовой системы с заданным поведением при неточных операциях
+    ///
+    pub fn new(policy: InexactPolicy) -> Self {
+        RationalBackend { policy }
+    }
+}
+
+impl NumericBackend for RationalBackend {
+    fn normalize(&self, value: Number) -> Result<Number, String> {
+        match (value, self.policy) {
+            (Number::Float(v), InexactPolicy::Float) => Ok(Number::Float(v)),
+            (Number::Float(v), InexactPolicy::Error) => Err(format!(
+                "результат операции не является рациональным числом (≈ {})",
+                v
+            )),
+            (value, _) => Ok(Number::Rational(value.to_rational()?)),
+        }
+    }
+}
+
+///
+/// Числовая система на основе комплексных чисел
+/// Компоненты, которые меньше модуля числа более чем в 10^15 раз, считаются погрешностью и обнуляются,
+///   чтобы, например, exp(i*pi) было равно -1, а не -1+1.2246467991473532e-16i
+///
+pub struct ComplexBackend;
+
+impl NumericBackend for ComplexBackend {
+    fn is_complex(&self) -> bool {
+        true
+    }
+
+    fn normalize(&self, value: Number) -> Result<Number, String> {
+        let mut z = value.to_complex();
+        let threshold = z.norm() * 1e-15;
+        if z.re.abs() < threshold {
+            z.re = 0.0;
+        }
+        if z.im.abs() < threshold {
+            z.im = 0.0;
+        }
+
+        Ok(Number::Complex(z))
+    }
+}
+
+///
+/// Числовая система на основе целых чисел произвольного размера
+/// Все операции выполняются точно, деление выполняется нацело.
+/// Дробные литералы и результаты функций с дробной частью являются ошибкой
+///
+pub struct IntegerBackend;
+
+impl NumericBackend for IntegerBackend {
+    fn normalize(&self, value: Number) -> Result<Number, String> {
+        // дробный литерал (например, 2.0) отвергается даже при нулевой дробной части
+        if let Number::Decimal(v) = &value {
+            if v.as_bigint_and_exponent().1 > 0 {
+                return Err(format!("дробное число {} недопустимо в целочисленном режиме", v));
+            }
+        }
+
+        match value.to_integer() {
+            Ok(v) => Ok(Number::Integer(v)),
+            Err(why) => Err(format!("{}, в целочисленном режиме допустимы только целые числа", why)),
+        }
+    }
+}
+
+// Базовые тесты
+#[cfg(test)]
+#[test]
+fn test_decimal_is_exact() {
+    let backend = DecimalBackend::new(28);
+    let a = backend.normalize(Number::parse("0.1").unwrap()).unwrap();
+    let b = backend.normalize(Number::parse("0.2").unwrap()).unwrap();
+
+    assert_eq!(a.add(b).unwrap().to_string(), "0.3");
+}
+
+#[test]
+fn test_decimal_power() {
+    let number = |s: &str| Number::parse(s).unwrap();
+
+    assert_eq!(number("0").pow(number("0")).unwrap().to_string(), "1");
+    assert_eq!(number("-1").pow(number("1000000001")).unwrap().to_string(), "-1");
+    assert_eq!(number("2").pow(number("-2")).unwrap().to_string(), "0.25");
+    assert!(number("2").pow(number("1000000000")).is_err());
+}
+
+#[test]
+fn test_decimal_precision() {
+    let backend = DecimalBackend::new(5);
+    let third = Number::parse("1").unwrap().div(Number::parse("3").unwrap()).unwrap();
+
+    assert_eq!(backend.normalize(third).unwrap().to_string(), "0.33333");
+}
+
+#[test]
+fn test_float_backend() {
+    let backend = FloatBackend {};
+    let a = backend.normalize(Number::parse("0.5").unwrap()).unwrap();
+
+    assert_eq!(a, Number::Float(0.5));
+}
+
+#[test]
+fn test_rational_is_exact() {
+    let backend = RationalBackend::new(InexactPolicy::Float);
+    let number = |s: &str| backend.normalize(Number::parse(s).unwrap()).unwrap();
+
+    let third = number("1").div(number("3")).unwrap();
+    assert_eq!(third.to_string(), "1/3");
+    assert_eq!(third.clone().mul(number("3")).unwrap(), number("1"));
+    assert_eq!(number("0.25").add(third.clone()).unwrap().to_string(), "7/12");
+    assert_eq!(number("2").pow(number("-2")).unwrap().to_string(), "1/4");
+    assert_eq!(number("4").div(number("9")).unwrap().sqrt().unwrap().to_string(), "2/3");
+
+    let format = NumberFormat { approximation: Some(4), ..NumberFormat::default() };
+    assert_eq!(format.format(&third), "1/3 ≈ 0.3333");
+}
+
+#[test]
+fn test_rational_inexact_policy() {
+    let two = Number::Rational(BigRational::from_integer(BigInt::from(2)));
+    let root = two.sqrt().unwrap();
+
+    assert!(matches!(
+        RationalBackend::new(InexactPolicy::Float).normalize(root.clone()),
+        Ok(Number::Float(_))
+    ));
+    assert!(RationalBackend::new(InexactPolicy::Error).normalize(root).is_err());
+}
+
+#[test]
+fn test_complex_arithmetic() {
+    let backend = ComplexBackend {};
+    let number = |s: &str| backend.normalize(Number::parse(s).unwrap()).unwrap();
+
+    assert_eq!(number("i").pow(number("2")).unwrap().to_string(), "-1");
+    assert_eq!(number("-4").sqrt().unwrap().to_string(), "2i");
+    assert_eq!(number("1").sub(number("2.5i")).unwrap().to_string(), "1-2.5i");
+    assert_eq!(number("3").add(number("4i")).unwrap().abs().unwrap().to_string(), "5");
+
+    let e_pi = number("i").mul(Number::Float(std::f64::consts::PI)).unwrap();
+    let e_pi = e_pi.apply_analytic("exp", f64::exp, Complex64::exp).unwrap();
+    assert_eq!(backend.normalize(e_pi).unwrap().to_string(), "-1");
+
+    let format = NumberFormat { polar: true, ..NumberFormat::default() };
+    assert_eq!(format.format(&number("-2")), format!("2∠{}", std::f64::consts::PI));
+}
+
+#[test]
+fn test_complex_zero_power() {
+    let backend = ComplexBackend {};
+    let number = |s: &str| backend.normalize(Number::parse(s).unwrap()).unwrap();
+
+    assert_eq!(number("0").pow(number("0")).unwrap().to_string(), "1");
+    assert_eq!(number("i").pow(number("0")).unwrap().to_string(), "1");
+    assert_eq!(number("0").pow(number("2")).unwrap().to_string(), "0");
+    assert!(number("0").pow(number("-1")).is_err());
+}
+
+#[test]
+fn test_complex_in_real_backend() {
+    let imaginary = Number::parse("2i").unwrap();
+
+    assert!(DecimalBackend::new(28).normalize(imaginary.clone()).is_err());
+    assert!(FloatBackend {}.normalize(imaginary).is_err());
+    assert_eq!(DecimalBackend::new(28).normalize(Number::Complex(Complex64::new(1.5, 0.0))).unwrap().to_string(), "1.5");
+}
+
+#[test]
+fn test_integer_is_exact() {
+    let backend = IntegerBackend {};
+    let number = |s: &str| backend.normalize(Number::parse(s).unwrap()).unwrap();
+
+    let big = number("1").shl(number("100")).unwrap();
+    assert_eq!(big.to_string(), "1267650600228229401496703205376");
+    assert_eq!(big.clone().shr(number("99")).unwrap(), number("2"));
+    assert_eq!(big.clone().bitor(number("1")).unwrap().bitxor(big).unwrap(), number("1"));
+    assert_eq!(number("12").bitand(number("10")).unwrap(), number("8"));
+    assert_eq!(number("5").not().unwrap(), number("-6"));
+    assert_eq!(number("-7").div(number("2")).unwrap(), number("-3"));
+    assert_eq!(number("-7").rem(number("3")).unwrap(), number("-1"));
+    assert_eq!(number("-7").modulo(number("3")).unwrap(), number("2"));
+    assert_eq!(number("-1").pow(number("4000000001")).unwrap(), number("-1"));
+    assert!(number("2").pow(number("4000000000")).is_err());
+}
+
+#[test]
+fn test_integer_rejects_fractions() {
+    let backend = IntegerBackend {};
+
+    assert!(backend.normalize(Number::parse("1.5").unwrap()).is_err());
+    assert!(backend.normalize(Number::parse("2.0").unwrap()).is_err());
+    assert!(backend.normalize(Number::Float(0.5)).is_err());
+    assert_eq!(backend.normalize(Number::Float(4.0)).unwrap().to_string(), "4");
+}
+
+#[test]
+fn test_integer_percent() {
+    assert!(Number::Integer(BigInt::from(10)).percent().is_err());
+    assert_eq!(Number::Integer(BigInt::from(-300)).percent().unwrap().to_string(), "-3");
+    assert_eq!(Number::parse("10").unwrap().percent().unwrap().to_string(), "0.1");
+}
+
+#[test]
+fn test_literal_forms() {
+    let parse = |s: &str| Number::parse(s).unwrap().to_string();
+
+    assert_eq!(parse("0xFF"), "255");
+    assert_eq!(parse("0b1010_1010"), "170");
+    assert_eq!(parse("0o17"), "15");
+    assert_eq!(parse("1e-9"), "0.000000001");
+    assert_eq!(parse("1_000.5E3"), "1000500");
+    assert_eq!(parse(".5"), "0.5");
+    assert!(Number::parse("0b102").is_err());
+
+    let format = NumberFormat { radix: Some(16), ..NumberFormat::default() };
+    assert_eq!(format.format(&Number::parse("-255").unwrap()), "-0xFF");
+    assert_eq!(format.format(&Number::parse("0.5").unwrap()), "0.5");
+}
+
+#[test]
+fn test_decimal_scale_limit() {
+    let number = |s: &str| Number::parse(s).unwrap();
+
+    assert!(Number::parse("1e100000000").is_err());
+    assert!(Number::parse("1e-100000000").is_err());
+    assert_eq!(number("1e1000").to_string().len(), 1001);
+    assert!(number("1e-50000").mul(number("1e-50000")).is_err());
+    assert!(number("1e50000").div(number("1e-50000")).is_err());
+    assert!(number("1e-60000").to_rational().is_ok());
+    assert!(number("0.1").pow(number("1000000")).is_err());
+}
@@ -1,162 +1,265 @@
-use std::cmp::Ordering;
+use std::fmt;
+use std::rc::Rc;
 
+use crate::error::CalcError;
+use crate::functions::Function;
 use crate::queue::Queue;
-use crate::token::{Token, TokenType};
+use crate::registry::{Associativity, Evaluator, OperatorRegistry};
+use crate::token::{Span, Token, TokenType};
+use crate::value::Value;
 
-// Определим псевдонимы типов для наглядности и удобства
-pub type Number = f32;
+// Числа, над которыми выполняются операции, определяются выбранной числовой системой
+pub use crate::number::Number;
 
 
 ///
 /// Перечисление типов использующихся в выражениях
 ///   - числа
+///   - логические значения
 ///   - операторы (функции также считаются операторами)
+///   - вызовы функций с количеством переданных аргументов
+///   - условное выражение, вычисляющее только выбранную ветвь
+///   - переменные, значения которых берутся из окружения калькулятора
+///   - именованные константы вместе с их значением из реестра
 ///
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Lexem {
     NumberLex(Number),
+    BoolLex(bool),
     OperatorLex(Operator),
+    CallLex(Operator, usize),
+    CondLex,
+    VariableLex(String),
+    ConstantLex(String, Number),
 }
 
-// Псевдоним типа для краткости записи
-pub type Expression = Queue<Lexem>;
+// Псевдоним типа для краткости записи - очередь лексем с их положением во входной строке
+pub type Expression = Queue<(Lexem, Span)>;
 
 // Реализация методов для перечисления Lexem
 impl Lexem {
     ///
-    /// Создание нового элемента перечисления
+    /// Создание нового элемента перечисления. Операторы и значения констант ищутся в заданном реестре
+    /// Некорректный числовой литерал и токен, не образующий лексему, являются ошибкой
     ///
-    pub fn new(tok: &Token) -> Self {
-        match tok.0 {
-            TokenType::NumberInt | TokenType::NumberFloat => {
-                Lexem::NumberLex(tok.1.parse::<Number>().unwrap())
+    pub fn new(tok: &Token, registry: &OperatorRegistry) -> Result<Self, CalcError> {
+        let lexem = match tok.0 {
+            TokenType::NumberInt | TokenType::NumberFloat | TokenType::NumberImaginary | TokenType::NumberRadix(_) => {
+                Lexem::NumberLex(Number::parse(&tok.1).map_err(|why| CalcError::Conversion(why, tok.2))?)
             }
-            TokenType::UnaryOperator | TokenType::BinaryOperator | TokenType::Function => {
-                Lexem::OperatorLex(Operator::get_operator(tok))
+            TokenType::UnaryOperator
+            | TokenType::PrefixOperator
+            | TokenType::BinaryOperator
+            | TokenType::PostfixOperator
+            | TokenType::Function => {
+                Lexem::OperatorLex(Operator::from_registry(registry, tok))
             }
-            _ => Lexem::NumberLex(0.0),
+            TokenType::Boolean => Lexem::BoolLex(tok.1 == "true"),
+            TokenType::Colon => Lexem::CondLex,
+            TokenType::Identifier => Lexem::VariableLex(tok.1.clone()),
+            TokenType::Constant => match registry.find_constant(&tok.1) {
+                Some(value) => Lexem::ConstantLex(tok.1.clone(), value),
+                None => Lexem::VariableLex(tok.1.clone()),
+            },
+            _ => return Err(CalcError::Conversion(format!("неожиданный токен '{}'", tok.1), tok.2)),
+        };
+
+        Ok(lexem)
+    }
+}
+
+// Печать лексемы в том виде, в котором она выводится в обратной польской нотации
+impl fmt::Display for Lexem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lexem::NumberLex(v) => write!(f, "{}", v),
+            Lexem::BoolLex(b) => write!(f, "{}", b),
+            Lexem::CondLex => write!(f, "?:"),
+            Lexem::OperatorLex(op) | Lexem::CallLex(op, _) => write!(f, "{}", op.name()),
+            Lexem::VariableLex(name) | Lexem::ConstantLex(name, _) => write!(f, "{}", name),
         }
     }
 }
 
 ///
 /// Базовый объект для представления операторов
+/// Операторы выполняются над значениями (`R` = `Value`), функции - над числами
 ///
-#[derive(Debug)]
-pub struct BaseOperator<T> {
+#[derive(Clone)]
+pub struct BaseOperator<T, R = Number> {
+    pub symbol: String,                // обозначение оператора во входной строке
     pub name: String,                  // наименование оператора. используется при печати выходного выражения
     priority: u32,                     // приоритет оператора
     is_left: bool,                     // является ли оператор левоассоциативным
-    pub apply: fn(T) -> Number,        // функция которую выполняет данный оператор
+    pub short_circuit: Option<bool>,   // значение левого операнда, при котором правый операнд не вычисляется
+    pub postfix: bool,                 // унарный оператор записывается после операнда
+    pub apply: Rc<dyn Fn(T) -> Result<R, String>>, // функция которую выполняет данный оператор
+}
+
+// Отладочная печать оператора без выполняемой им функции
+impl<T, R> fmt::Debug for BaseOperator<T, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BaseOperator")
+            .field("symbol", &self.symbol)
+            .field("name", &self.name)
+            .field("priority", &self.priority)
+            .field("is_left", &self.is_left)
+            .field("short_circuit", &self.short_circuit)
+            .field("postfix", &self.postfix)
+            .finish()
+    }
 }
 
 ///
 /// Перечисление известных типов операторов
 ///
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Operator {
-    Unary(BaseOperator<Number>),                          // унарные
-    Binary(BaseOperator<(Number, Number)>),               // бинарные
-    #[allow(dead_code)]
+    Unary(BaseOperator<Value, Value>),                    // унарные
+    Binary(BaseOperator<(Value, Value), Value>),          // бинарные
+    Function(BaseOperator<Number>),                       // функции одного аргумента
     BinaryFunction(BaseOperator<(Number, Number)>),       // бинарные функции
-    Unknown,                                              // Ошибочный (неизвестный) оператор
+    VariadicFunction(BaseOperator<Vec<Number>>),          // функции с произвольным числом аргументов
+    Unknown(String),                                      // Ошибочный (неизвестный) оператор
 }
 
-// Специализация функций создания нового оператора для двух обобщенных типов
-impl BaseOperator<Number> {
-    fn new(n: String, p: u32, l: bool, f: fn(Number) -> Number) -> Operator {
-        Operator::Unary(BaseOperator::<Number> {
-            name: n,
-            priority: p,
-            is_left: l,
+// Создание оператора-функции. Приоритет и ассоциативность для функций не используются
+impl<T> BaseOperator<T> {
+    fn function(name: &str, f: Rc<dyn Fn(T) -> Result<Number, String>>) -> Self {
+        BaseOperator::<T> {
+            symbol: name.to_string(),
+            name: name.to_string(),
+            priority: 0,
+            is_left: true,
+            short_circuit: None,
+            postfix: false,
             apply: f,
-        })
+        }
     }
 }
 
-impl BaseOperator<(Number, Number)> {
-    fn new(n: String, p: u32, l: bool, f: fn((Number, Number)) -> Number) -> Operator {
-        Operator::Binary(BaseOperator::<(Number, Number)> {
-            name: n,
-            priority: p,
-            is_left: l,
-            apply: f,
-        })
+// реализация методов объекта операторов
+impl Operator {
+    ///
+    /// Создание оператора по токену с использованием реестра стандартных операторов
+    ///
+    pub fn get_operator(tok: &Token) -> Operator {
+        Operator::from_registry(&OperatorRegistry::standard(), tok)
     }
-}
 
-// Имплементация необходимых типажей (PartialOrd, PartialEq) для возможности сравнения операторов
-impl PartialOrd for Operator {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let (p_lhs, _) = match self {
-            Operator::Unary(op) => (op.priority, op.is_left),
-            Operator::Binary(op) => (op.priority, op.is_left),
-            _ => return None,
-        };
-
-        let (p_rhs, _) = match other {
-            Operator::Binary(op) => (op.priority, op.is_left),
-            Operator::Unary(op) => (op.priority, op.is_left),
-            _ => return None,
+    ///
+    /// Создание оператора по токену с использованием заданного реестра операторов
+    /// Операторы и функции, отсутствующие в реестре и таблице функций, считаются неизвестными
+    ///
+    pub fn from_registry(registry: &OperatorRegistry, tok: &Token) -> Operator {
+        let arity = match tok.0 {
+            TokenType::UnaryOperator | TokenType::PrefixOperator | TokenType::PostfixOperator => 1,
+            TokenType::BinaryOperator => 2,
+            TokenType::Function => {
+                let name = tok.1.as_str();
+                return match registry.find_function(name) {
+                    Some(Function::Unary(f)) => Operator::Function(BaseOperator::function(name, f)),
+                    Some(Function::Binary(f)) => Operator::BinaryFunction(BaseOperator::function(name, f)),
+                    Some(Function::Variadic(f)) => Operator::VariadicFunction(BaseOperator::function(name, f)),
+                    None => Operator::Unknown(name.to_string()),
+                };
+            }
+            _ => return Operator::Unknown(tok.1.clone()),
         };
 
-        p_lhs.partial_cmp(&p_rhs)
-    }
-}
-
-impl PartialEq for Operator {
-    fn eq(&self, other: &Self) -> bool {
-        let (p_lhs, l_lhs) = match self {
-            Operator::Unary(op) => (op.priority, op.is_left),
-            Operator::Binary(op) => (op.priority, op.is_left),
-            _ => return false,
+        let def = match tok.0 {
+            TokenType::PostfixOperator => registry.find_postfix(&tok.1),
+            _ => registry.find(&tok.1, arity),
         };
-
-        let (p_rhs, _) = match other {
-            Operator::Binary(op) => (op.priority, op.is_left),
-            Operator::Unary(op) => (op.priority, op.is_left),
-            _ => return false,
+        let def = match def {
+            Some(def) => def,
+            None => return Operator::Unknown(tok.1.clone()),
         };
 
-        if l_lhs {
-            p_lhs == p_rhs
-        } else {
-            false
+        let is_left = def.associativity == Associativity::Left;
+        match &def.evaluator {
+            Evaluator::Unary(f) => Operator::Unary(BaseOperator::<Value, Value> {
+                symbol: def.symbol.clone(),
+                name: def.name.clone(),
+                priority: def.precedence,
+                is_left,
+                short_circuit: def.short_circuit,
+                postfix: def.postfix,
+                apply: f.clone(),
+            }),
+            Evaluator::Binary(f) => Operator::Binary(BaseOperator::<(Value, Value), Value> {
+                symbol: def.symbol.clone(),
+                name: def.name.clone(),
+                priority: def.precedence,
+                is_left,
+                short_circuit: def.short_circuit,
+                postfix: def.postfix,
+                apply: f.clone(),
+            }),
         }
     }
-}
 
-// реализация методов объекта операторов
-// для добавления новых операторов следует добавлять их здесь
-impl Operator {
-    pub fn get_operator(tok: &Token) -> Operator {
-        match tok.1.as_ref() {
-            "+" if tok.0 == TokenType::UnaryOperator => {
-                BaseOperator::<Number>::new("POS".to_string(), 1, true, |x| x)
-            }
-            "-" if tok.0 == TokenType::UnaryOperator => {
-                BaseOperator::<Number>::new("NEG".to_string(), 1, true, |x| -1.0 * x)
+    ///
+    /// Проверка, должен ли оператор `top` с вершины стека быть переложен в выходную очередь
+    ///   перед помещением в стек данного оператора:
+    ///   `top` связывает сильнее, либо приоритеты равны и данный оператор левоассоциативен
+    ///
+    pub fn yields_to(&self, top: &Operator) -> bool {
+        match (self.precedence(), top.precedence()) {
+            (Some((priority, is_left)), Some((top_priority, _))) => {
+                top_priority < priority || (top_priority == priority && is_left)
             }
+            _ => false,
+        }
+    }
 
-            ">>" => BaseOperator::<(Number, Number)>::new("".to_string(), 4, true, |(x, y)| {
-                ((x as i32) >> (y as i32)) as Number
-            }),
-            "<<" => BaseOperator::<(Number, Number)>::new("".to_string(), 4, true, |(x, y)| {
-                ((x as i32) << (y as i32)) as Number
-            }),
-
-            "+" => BaseOperator::<(Number, Number)>::new("+".to_string(), 3, true, |(x, y)| x + y),
-            "-" => BaseOperator::<(Number, Number)>::new("-".to_string(), 3, true, |(x, y)| x - y),
+    ///
+    /// Наименование оператора для печати выражения в обратной польской нотации
+    ///
+    pub fn name(&self) -> &str {
+        match self {
+            Operator::Unary(op) => &op.name,
+            Operator::Binary(op) => &op.name,
+            Operator::Function(op) => &op.name,
+            Operator::BinaryFunction(op) => &op.name,
+            Operator::VariadicFunction(op) => &op.name,
+            Operator::Unknown(name) => name,
+        }
+    }
 
-            "/" => BaseOperator::<(Number, Number)>::new("/".to_string(), 2, true, |(x, y)| x / y),
-            "*" => BaseOperator::<(Number, Number)>::new("×".to_string(), 2, true, |(x, y)| x * y),
-            "%" => BaseOperator::<(Number, Number)>::new("%".to_string(), 2, true, |(x, y)| x % y),
+    ///
+    /// Обозначение оператора во входной строке. Используется при печати выражения в инфиксной форме
+    ///
+    pub fn symbol(&self) -> &str {
+        match self {
+            Operator::Unary(op) => &op.symbol,
+            Operator::Binary(op) => &op.symbol,
+            Operator::Function(op) => &op.symbol,
+            Operator::BinaryFunction(op) => &op.symbol,
+            Operator::VariadicFunction(op) => &op.symbol,
+            Operator::Unknown(name) => name,
+        }
+    }
 
-            "^" => BaseOperator::<(Number, Number)>::new("pow".to_string(), 5, false, |(x, y)| {
-                x.powf(y)
-            }),
+    ///
+    /// Является ли оператор постфиксным
+    ///
+    pub fn is_postfix(&self) -> bool {
+        match self {
+            Operator::Unary(op) => op.postfix,
+            _ => false,
+        }
+    }
 
-            _ => Operator::Unknown,
+    ///
+    /// Приоритет и левоассоциативность оператора. Для функций не определены
+    ///
+    pub fn precedence(&self) -> Option<(u32, bool)> {
+        match self {
+            Operator::Unary(op) => Some((op.priority, op.is_left)),
+            Operator::Binary(op) => Some((op.priority, op.is_left)),
+            _ => None,
         }
     }
 }
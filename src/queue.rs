@@ -5,6 +5,13 @@ pub struct Queue<T> {
     pub queue: Vec<T>,
 }
 
+// Имплементация типажа для возможности создания объекта со значениями по-умолчанию
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Реализация методов очереди
 impl<T> Queue<T> {
     pub fn new() -> Self {
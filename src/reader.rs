@@ -1,18 +1,21 @@
+use std::cell::RefCell;
+use std::fs::File;
 use std::io;
-use std::io::Write;
+use std::io::{BufRead, BufReader, Write};
 
 ///
 /// Типаж для определения объектов получающих входную строку
+/// Возвращает None, если входные данные закончились
 ///
 pub trait Reader {
-    fn read(&self) -> Result<String, String>;
+    fn read(&self) -> Result<Option<String>, String>;
 }
 
 // Объект заглушка
 pub struct EmptyInput {}
 impl Reader for EmptyInput {
-    fn read(&self) -> Result<String, String> {
-        Ok(String::new())
+    fn read(&self) -> Result<Option<String>, String> {
+        Ok(None)
     }
 }
 
@@ -24,7 +27,7 @@ impl Reader for ConsoleReader {
     ///
     /// Чтение строки из стандартного потока ввода
     ///
-    fn read(&self) -> Result<String, String> {
+    fn read(&self) -> Result<Option<String>, String> {
         let stdin = io::stdin();
         let mut input = String::new();
         print!("Введите выражение: ");
@@ -32,8 +35,81 @@ impl Reader for ConsoleReader {
         // После последнего match сознательно пропускаем ';'
         // т.к. это должно быть выражением для возврата значения из функции
         match stdin.read_line(&mut input) {
-            Ok(_) => Ok(input.trim().replace(" ", "").to_string()),
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(input.trim().to_string())),
             Err(_) => Err(String::from("Не удалось прочитать строку")),
         }
     }
 }
+
+///
+/// Объект построчно читающий выражения из файла или стандартного потока ввода без вывода приглашения
+/// Пустые строки и строки комментариев, начинающиеся с символа '#', пропускаются
+///
+pub struct BatchReader {
+    source: RefCell<Box<dyn BufRead>>,
+}
+
+// Реализация методов пакетного чтения
+impl BatchReader {
+    ///
+    /// Чтение из произвольного источника
+    ///
+    pub fn new(source: Box<dyn BufRead>) -> Self {
+        BatchReader {
+            source: RefCell::new(source),
+        }
+    }
+
+    ///
+    /// Чтение из файла
+    ///
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        match File::open(path) {
+            Ok(file) => Ok(BatchReader::new(Box::new(BufReader::new(file)))),
+            Err(why) => Err(format!("Не удалось открыть файл {}: {}", path, why)),
+        }
+    }
+
+    ///
+    /// Чтение из стандартного потока ввода (например, из канала)
+    ///
+    pub fn stdin() -> Self {
+        BatchReader::new(Box::new(BufReader::new(io::stdin())))
+    }
+}
+
+// Реализация типажа Reader
+impl Reader for BatchReader {
+    ///
+    /// Чтение следующей строки, содержащей выражение
+    ///
+    fn read(&self) -> Result<Option<String>, String> {
+        let mut source = self.source.borrow_mut();
+        loop {
+            let mut input = String::new();
+            match source.read_line(&mut input) {
+                Ok(0) => return Ok(None),
+                Ok(_) => {
+                    let line = input.trim();
+                    if !line.is_empty() && !line.starts_with('#') {
+                        return Ok(Some(line.to_string()));
+                    }
+                }
+                Err(_) => return Err(String::from("Не удалось прочитать строку")),
+            }
+        }
+    }
+}
+
+// Базовые тесты
+#[cfg(test)]
+#[test]
+fn test_batch_reader_skips_comments() {
+    let input = "# комментарий\n\n1+2\n   \n  # отступ\n x = 3 \n";
+    let reader = BatchReader::new(Box::new(io::Cursor::new(input)));
+
+    assert_eq!(reader.read(), Ok(Some("1+2".to_string())));
+    assert_eq!(reader.read(), Ok(Some("x = 3".to_string())));
+    assert_eq!(reader.read(), Ok(None));
+}
@@ -0,0 +1,442 @@
+//!
+//! Реестр операторов: единое декларативное описание всех операторов калькулятора
+//! Реестр используется разборщиком (обозначения операторов), преобразователем (приоритет и ассоциативность)
+//!   и вычислителем (функция, выполняемая оператором)
+//! Помимо операторов реестр содержит функции, вызываемые по имени, и именованные константы
+//!
+use std::rc::Rc;
+
+use crate::constants::builtin_constants;
+use crate::functions::{builtin_functions, Function};
+use crate::number::Number;
+use crate::value::{BinaryValueFunction, UnaryValueFunction, Value};
+
+///
+/// Ассоциативность оператора
+///
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+///
+/// Функция, выполняемая оператором. Вид функции определяет арность оператора
+/// Операторы выполняются над значениями: числами и логическими значениями
+///
+#[derive(Clone)]
+pub enum Evaluator {
+    Unary(UnaryValueFunction),
+    Binary(BinaryValueFunction),
+}
+
+// Имя функции условного выражения `if(условие, a, b)`, которая вычисляет только выбранную ветвь
+pub const CONDITIONAL_FUNCTION: &str = "if";
+
+// Наименование постфиксного оператора процента. В сумме и разности процент берется от левого операнда:
+//   `200 + 10%` = 220
+pub const PERCENT_OPERATOR: &str = "percent";
+
+///
+/// Описание оператора
+/// Приоритеты соответствуют уровням приоритетов языка С: чем меньше значение, тем сильнее связывает оператор
+///
+#[derive(Clone)]
+pub struct OperatorDef {
+    pub symbol: String,                // обозначение оператора во входной строке
+    pub name: String,                  // наименование оператора. используется при печати выходного выражения
+    pub precedence: u32,               // приоритет оператора
+    pub associativity: Associativity,  // ассоциативность оператора
+    pub evaluator: Evaluator,          // функция которую выполняет данный оператор
+    pub short_circuit: Option<bool>,   // значение левого операнда, при котором правый операнд не вычисляется
+    pub postfix: bool,                 // унарный оператор записывается после операнда: `5!`
+}
+
+// Реализация методов описания оператора
+impl OperatorDef {
+    ///
+    /// Описание унарного (префиксного) оператора над числами
+    ///
+    pub fn unary(
+        symbol: &str,
+        name: &str,
+        precedence: u32,
+        f: impl Fn(Number) -> Result<Number, String> + 'static,
+    ) -> Self {
+        OperatorDef::unary_value(symbol, name, precedence, move |x| f(x.number()?).map(Value::Number))
+    }
+
+    ///
+    /// Описание постфиксного оператора над числами: `5!`, `10%`
+    /// Постфиксный оператор применяется к операнду сразу, поэтому связывает сильнее операторов перед операндом
+    ///
+    pub fn postfix(
+        symbol: &str,
+        name: &str,
+        precedence: u32,
+        f: impl Fn(Number) -> Result<Number, String> + 'static,
+    ) -> Self {
+        OperatorDef {
+            associativity: Associativity::Left,
+            postfix: true,
+            ..OperatorDef::unary(symbol, name, precedence, f)
+        }
+    }
+
+    ///
+    /// Описание бинарного оператора над числами
+    ///
+    pub fn binary(
+        symbol: &str,
+        name: &str,
+        precedence: u32,
+        associativity: Associativity,
+        f: impl Fn((Number, Number)) -> Result<Number, String> + 'static,
+    ) -> Self {
+        OperatorDef::binary_value(symbol, name, precedence, associativity, move |(x, y)| {
+            f((x.number()?, y.number()?)).map(Value::Number)
+        })
+    }
+
+    ///
+    /// Описание унарного (префиксного) оператора над значениями любого типа
+    ///
+    pub fn unary_value(
+        symbol: &str,
+        name: &str,
+        precedence: u32,
+        f: impl Fn(Value) -> Result<Value, String> + 'static,
+    ) -> Self {
+        OperatorDef {
+            symbol: symbol.to_string(),
+            name: name.to_string(),
+            precedence,
+            associativity: Associativity::Right,
+            evaluator: Evaluator::Unary(Rc::new(f)),
+            short_circuit: None,
+            postfix: false,
+        }
+    }
+
+    ///
+    /// Описание бинарного оператора над значениями любого типа (сравнения, логические операторы)
+    ///
+    pub fn binary_value(
+        symbol: &str,
+        name: &str,
+        precedence: u32,
+        associativity: Associativity,
+        f: impl Fn((Value, Value)) -> Result<Value, String> + 'static,
+    ) -> Self {
+        OperatorDef {
+            symbol: symbol.to_string(),
+            name: name.to_string(),
+            precedence,
+            associativity,
+            evaluator: Evaluator::Binary(Rc::new(f)),
+            short_circuit: None,
+            postfix: false,
+        }
+    }
+
+    ///
+    /// Вычисление бинарного оператора по короткой схеме: если левый операнд равен заданному логическому значению,
+    ///   то правый операнд не вычисляется, а результатом является левый операнд (`false && x`, `true || x`)
+    ///
+    pub fn short_circuit(mut self, value: bool) -> Self {
+        self.short_circuit = Some(value);
+        self
+    }
+
+    ///
+    /// Количество аргументов оператора
+    ///
+    pub fn arity(&self) -> usize {
+        match self.evaluator {
+            Evaluator::Unary(_) => 1,
+            Evaluator::Binary(_) => 2,
+        }
+    }
+}
+
+///
+/// Реестр операторов, функций и констант
+///
+#[derive(Clone)]
+pub struct OperatorRegistry {
+    operators: Vec<OperatorDef>,
+    functions: Vec<(String, Function)>,
+    constants: Vec<(String, Number)>,
+}
+
+// Реестр по-умолчанию содержит стандартные операторы
+impl Default for OperatorRegistry {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+// Реализация методов реестра операторов
+// для добавления новых стандартных операторов следует добавлять их здесь
+impl OperatorRegistry {
+    ///
+    /// Реестр без операторов
+    ///
+    pub fn empty() -> Self {
+        OperatorRegistry {
+            operators: Vec::new(),
+            functions: Vec::new(),
+            constants: Vec::new(),
+        }
+    }
+
+    ///
+    /// Реестр стандартных операторов, встроенных функций и констант
+    ///
+    pub fn standard() -> Self {
+        use std::cmp::Ordering::{Greater, Less};
+        use Associativity::{Left, Right};
+
+        OperatorRegistry {
+            operators: vec![
+                OperatorDef::postfix("!", "fact", 0, Number::factorial),
+                OperatorDef::postfix("!!", "fact2", 0, Number::double_factorial),
+                OperatorDef::postfix("%", PERCENT_OPERATOR, 0, Number::percent),
+                OperatorDef::binary("^", "pow", 1, Right, |(x, y)| x.pow(y)),
+                OperatorDef::unary("+", "POS", 2, Ok),
+                OperatorDef::unary("-", "NEG", 2, Number::neg),
+                OperatorDef::unary("~", "NOT", 2, Number::not),
+                OperatorDef::unary("√", "sqrt", 2, Number::sqrt),
+                OperatorDef::unary_value("!", "!", 2, Value::logical_not),
+                OperatorDef::binary("*", "×", 3, Left, |(x, y)| x.mul(y)),
+                OperatorDef::binary("/", "/", 3, Left, |(x, y)| x.div(y)),
+                OperatorDef::binary("%", "%", 3, Left, |(x, y)| x.rem(y)),
+                OperatorDef::binary("mod", "mod", 3, Left, |(x, y)| x.modulo(y)),
+                OperatorDef::binary("+", "+", 4, Left, |(x, y)| x.add(y)),
+                OperatorDef::binary("-", "-", 4, Left, |(x, y)| x.sub(y)),
+                OperatorDef::binary("<<", "<<", 5, Left, |(x, y)| x.shl(y)),
+                OperatorDef::binary(">>", ">>", 5, Left, |(x, y)| x.shr(y)),
+                OperatorDef::binary_value("<", "<", 6, Left, |(x, y)| Ok(Value::Bool(x.compare(y)? == Less))),
+                OperatorDef::binary_value("<=", "<=", 6, Left, |(x, y)| Ok(Value::Bool(x.compare(y)? != Greater))),
+                OperatorDef::binary_value(">", ">", 6, Left, |(x, y)| Ok(Value::Bool(x.compare(y)? == Greater))),
+                OperatorDef::binary_value(">=", ">=", 6, Left, |(x, y)| Ok(Value::Bool(x.compare(y)? != Less))),
+                OperatorDef::binary_value("==", "==", 7, Left, |(x, y)| Ok(Value::Bool(x.equals(y)?))),
+                OperatorDef::binary_value("!=", "!=", 7, Left, |(x, y)| Ok(Value::Bool(!x.equals(y)?))),
+                OperatorDef::binary("&", "&", 8, Left, |(x, y)| x.bitand(y)),
+                OperatorDef::binary("xor", "xor", 9, Left, |(x, y)| x.bitxor(y)),
+                OperatorDef::binary("|", "|", 10, Left, |(x, y)| x.bitor(y)),
+                OperatorDef::binary_value("&&", "&&", 11, Left, |(x, y)| x.and(y)).short_circuit(false),
+                OperatorDef::binary_value("||", "||", 12, Left, |(x, y)| x.or(y)).short_circuit(true),
+            ],
+            functions: builtin_functions()
+                .into_iter()
+                .map(|(name, f)| (name.to_string(), f))
+                .collect(),
+            constants: builtin_constants()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), Number::parse(value).unwrap()))
+                .collect(),
+        }
+    }
+
+    ///
+    /// Добавление оператора
+    /// Ошибкой является некорректное обозначение, либо наличие оператора с тем же обозначением и количеством аргументов
+    ///
+    pub fn add_operator(&mut self, def: OperatorDef) -> Result<(), String> {
+        let symbol = def.symbol.as_str();
+        let is_word = symbol.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if symbol.is_empty()
+            || symbol == "="
+            || symbol == "true"
+            || symbol == "false"
+            || symbol.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+            || symbol.contains(|c: char| c.is_whitespace() || "(),?:".contains(c))
+            || (is_word && !symbol.chars().all(|c| c.is_alphabetic() || c == '_'))
+        {
+            return Err(format!("некорректное обозначение оператора '{}'", symbol));
+        }
+
+        if def.postfix && self.find_postfix(symbol).is_some() {
+            return Err(format!("постфиксный оператор '{}' уже определен", symbol));
+        }
+
+        if !def.postfix && self.find(symbol, def.arity()).is_some() {
+            return Err(format!("оператор '{}' с количеством аргументов {} уже определен", symbol, def.arity()));
+        }
+
+        if is_word && self.find_function(symbol).is_some() {
+            return Err(format!("обозначение оператора '{}' совпадает с именем функции", symbol));
+        }
+
+        if is_word && self.find_constant(symbol).is_some() {
+            return Err(format!("обозначение оператора '{}' совпадает с именем константы", symbol));
+        }
+
+        self.operators.push(def);
+        Ok(())
+    }
+
+    ///
+    /// Добавление функции
+    /// Ошибкой является некорректное имя, либо наличие функции или оператора с тем же именем
+    ///
+    pub fn add_function(&mut self, name: &str, f: Function) -> Result<(), String> {
+        if !is_identifier(name) {
+            return Err(format!("некорректное имя функции '{}'", name));
+        }
+
+        if self.find_function(name).is_some() {
+            return Err(format!("функция '{}' уже определена", name));
+        }
+
+        if self.operators.iter().any(|op| op.symbol == name) {
+            return Err(format!("имя функции '{}' совпадает с обозначением оператора", name));
+        }
+
+        if self.find_constant(name).is_some() {
+            return Err(format!("имя функции '{}' совпадает с именем константы", name));
+        }
+
+        self.functions.push((name.to_string(), f));
+        Ok(())
+    }
+
+    ///
+    /// Добавление константы, либо переопределение значения уже известной константы
+    /// Ошибкой является некорректное имя, либо наличие функции или оператора с тем же именем
+    ///
+    pub fn set_constant(&mut self, name: &str, value: Number) -> Result<(), String> {
+        if !is_identifier(name) {
+            return Err(format!("некорректное имя константы '{}'", name));
+        }
+
+        if self.find_function(name).is_some() {
+            return Err(format!("имя константы '{}' совпадает с именем функции", name));
+        }
+
+        if self.operators.iter().any(|op| op.symbol == name) {
+            return Err(format!("имя константы '{}' совпадает с обозначением оператора", name));
+        }
+
+        match self.constants.iter_mut().find(|(n, _)| n == name) {
+            Some(constant) => constant.1 = value,
+            None => self.constants.push((name.to_string(), value)),
+        }
+        Ok(())
+    }
+
+    ///
+    /// Поиск функции по имени
+    ///
+    pub fn find_function(&self, name: &str) -> Option<Function> {
+        self.functions
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, f)| f.clone())
+    }
+
+    ///
+    /// Поиск значения константы по имени
+    ///
+    pub fn find_constant(&self, name: &str) -> Option<Number> {
+        self.constants
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    ///
+    /// Все константы реестра
+    ///
+    pub fn constants(&self) -> &[(String, Number)] {
+        &self.constants
+    }
+
+    ///
+    /// Поиск префиксного или бинарного оператора по обозначению и количеству аргументов
+    ///
+    pub fn find(&self, symbol: &str, arity: usize) -> Option<&OperatorDef> {
+        self.operators
+            .iter()
+            .find(|op| op.symbol == symbol && op.arity() == arity && !op.postfix)
+    }
+
+    ///
+    /// Поиск постфиксного оператора по обозначению
+    ///
+    pub fn find_postfix(&self, symbol: &str) -> Option<&OperatorDef> {
+        self.operators.iter().find(|op| op.symbol == symbol && op.postfix)
+    }
+
+    ///
+    /// Все операторы реестра
+    ///
+    pub fn operators(&self) -> &[OperatorDef] {
+        &self.operators
+    }
+}
+
+// Имена функций и констант записываются так же, как имена переменных
+//   и не совпадают с логическими литералами и функцией условного выражения
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !["true", "false", CONDITIONAL_FUNCTION].contains(&name)
+}
+
+// Базовые тесты
+#[cfg(test)]
+#[test]
+fn test_registry_find() {
+    let registry = OperatorRegistry::standard();
+
+    let minus = registry.find("-", 1).unwrap();
+    assert_eq!((minus.name.as_str(), minus.arity()), ("NEG", 1));
+
+    let pow = registry.find("^", 2).unwrap();
+    assert_eq!(pow.associativity, Associativity::Right);
+    assert!(pow.precedence < minus.precedence);
+
+    assert!(registry.find("^", 1).is_none());
+    assert_eq!(registry.find("!", 1).unwrap().name, "!");
+    assert_eq!(registry.find_postfix("!").unwrap().name, "fact");
+    assert!(registry.find("$", 2).is_none());
+}
+
+#[test]
+fn test_registry_conflicts() {
+    let mut registry = OperatorRegistry::standard();
+
+    // оператор с тем же обозначением и количеством аргументов
+    assert!(registry
+        .add_operator(OperatorDef::binary("+", "plus", 4, Associativity::Left, |(x, y)| x.add(y)))
+        .is_err());
+    // некорректное обозначение
+    assert!(registry.add_operator(OperatorDef::unary("(", "P", 2, Ok)).is_err());
+    // имя встроенной функции
+    assert!(registry.add_function("sin", Function::unary(Ok)).is_err());
+    assert!(registry.add_function("2x", Function::unary(Ok)).is_err());
+
+    // унарный оператор с обозначением бинарного допустим
+    assert!(registry.add_operator(OperatorDef::unary("*", "DEREF", 2, Ok)).is_ok());
+    assert!(registry.add_function("double", Function::unary(|x| x.clone().add(x))).is_ok());
+    assert!(registry.find_function("double").is_some());
+}
+
+#[test]
+fn test_registry_constants() {
+    let mut registry = OperatorRegistry::standard();
+    assert_eq!(registry.find_constant("c").unwrap().to_string(), "299792458");
+
+    // встроенную константу можно переопределить, новую - добавить
+    assert!(registry.set_constant("g", Number::parse("9.81").unwrap()).is_ok());
+    assert!(registry.set_constant("G", Number::parse("6.674e-11").unwrap()).is_ok());
+    assert_eq!(registry.find_constant("g").unwrap().to_string(), "9.81");
+    assert_eq!(registry.constants().len(), 10);
+
+    assert!(registry.set_constant("sin", Number::Float(0.0)).is_err());
+    assert!(registry.set_constant("mod", Number::Float(0.0)).is_err());
+    assert!(registry.add_function("pi", Function::unary(Ok)).is_err());
+}
@@ -0,0 +1,326 @@
+//!
+//! Стандартные правила проверки списка токенов для валидатора
+//!
+use crate::error::CalcError;
+use crate::token::{Span, Token, TokenList, TokenType};
+use crate::validator::Validator;
+
+///
+/// Валидатор со стандартным набором правил
+///
+pub fn standard_validator() -> Validator {
+    let mut validator = Validator::new();
+    validator
+        .add_rule(check_for_function_call)
+        .add_rule(check_for_assignment)
+        .add_rule(check_for_binary_operator)
+        .add_rule(check_for_repeate_binary_operator);
+
+    validator
+}
+
+///
+/// Валидатор со стандартным набором правил и неявным умножением: `2(3+4)`, `2pi`, `3sin(x)`
+///
+pub fn implicit_multiplication_validator() -> Validator {
+    let mut validator = standard_validator();
+    validator
+        .add_rule(check_for_implicit_multiplication)
+        .add_warning(warn_ambiguous_implicit_multiplication);
+
+    validator
+}
+
+///
+/// Вспомогательные функции передаваемые объекту валидатору для проверки введенных токенов
+/// При первом разборе на токены все операторы сложения и вычитания по-умолчанию считаются
+///  унарными. После успешного разбора на токены проходим по их списку с целью поиска реальных
+///  унарных операторов и заменяем на бинарные где это необходимо.
+/// Бинарность определяется по наличию перед проверяемым токеном закрывающей скобки, числа, логического литерала,
+///  переменной, константы или постфиксного оператора
+///
+pub fn check_for_binary_operator(list: &mut TokenList) -> Result<(), CalcError> {
+    let mut ind: usize = 0;
+    let list_size: usize = list.len();
+
+    while ind < list_size {
+        let tok: &Token = list.get(ind).unwrap();
+        if tok.0 == TokenType::UnaryOperator && ind > 0 {
+            let prev: &Token = list.get(ind - 1).unwrap();
+            if prev.0.is_number()
+                || prev.0 == TokenType::Boolean
+                || prev.0 == TokenType::Identifier
+                || prev.0 == TokenType::Constant
+                || prev.0 == TokenType::ClosedParenthesis
+                || prev.0 == TokenType::PostfixOperator
+            {
+                list.get_mut(ind).unwrap().0 = TokenType::BinaryOperator;
+            }
+        }
+        ind += 1;
+    }
+
+    Ok(())
+}
+
+///
+/// Вспомогательные функции передаваемые объекту валидатору для проверки введенных токенов
+/// Проверка на наличие двух следующих подряд бинарных операторов
+///
+pub fn check_for_repeate_binary_operator(list: &mut TokenList) -> Result<(), CalcError> {
+    let mut ind: usize = 0;
+    let list_size: usize = list.len();
+
+    while ind < list_size {
+        let tok: &Token = list.get(ind).unwrap();
+        if tok.0 == TokenType::BinaryOperator && ind > 0 {
+            let prev: &Token = list.get(ind - 1).unwrap();
+            if prev.0 == TokenType::BinaryOperator {
+                return Err(CalcError::Validation(
+                    "два бинарных оператора подряд".to_string(),
+                    tok.2,
+                ));
+            }
+        }
+        ind += 1;
+    }
+
+    Ok(())
+}
+
+///
+/// Вспомогательные функции передаваемые объекту валидатору для проверки введенных токенов
+/// При разборе на токены все имена считаются переменными. Имя, за которым следует
+///  открывающая скобка, является вызовом функции и заменяется на токен функции
+///
+pub fn check_for_function_call(list: &mut TokenList) -> Result<(), CalcError> {
+    let mut ind: usize = 0;
+    let list_size: usize = list.len();
+
+    while ind + 1 < list_size {
+        if list[ind].0 == TokenType::Identifier && list[ind + 1].0 == TokenType::OpenedParenthesis {
+            list[ind].0 = TokenType::Function;
+        }
+        ind += 1;
+    }
+
+    Ok(())
+}
+
+///
+/// Вспомогательные функции передаваемые объекту валидатору для проверки введенных токенов
+/// Присваивание допустимо только в виде `имя = выражение`, либо в виде определения функции
+///  `имя(параметры) = выражение`
+///
+pub fn check_for_assignment(list: &mut TokenList) -> Result<(), CalcError> {
+    let mut ind: usize = 0;
+    let list_size: usize = list.len();
+
+    while ind < list_size {
+        let is_variable = ind == 1 && list[0].0 == TokenType::Identifier;
+        if list[ind].0 == TokenType::Assignment
+            && (!(is_variable || is_definition_header(&list[..ind])) || ind + 1 == list_size)
+        {
+            return Err(CalcError::Validation(
+                "присваивание допустимо только в виде `имя = выражение` или `имя(параметры) = выражение`"
+                    .to_string(),
+                list[ind].2,
+            ));
+        }
+        ind += 1;
+    }
+
+    Ok(())
+}
+
+///
+/// Проверка заголовка определения пользовательской функции: `имя(параметр, ...)`
+/// Параметрами могут быть только имена, список параметров может быть пустым
+///
+pub fn is_definition_header(list: &[Token]) -> bool {
+    let size = list.len();
+    if size < 3
+        || list[0].0 != TokenType::Function
+        || list[1].0 != TokenType::OpenedParenthesis
+        || list[size - 1].0 != TokenType::ClosedParenthesis
+    {
+        return false;
+    }
+
+    // имена и разделители чередуются, список не может заканчиваться разделителем
+    let params = &list[2..size - 1];
+    (params.is_empty() || params.len() % 2 == 1)
+        && params.iter().enumerate().all(|(ind, tok)| match ind % 2 {
+            0 => tok.0 == TokenType::Identifier,
+            _ => tok.0 == TokenType::ArgumentSeparator,
+        })
+}
+
+///
+/// Вспомогательные функции передаваемые объекту валидатору для проверки введенных токенов
+/// Между операндом (число, переменная, константа, закрывающая скобка, постфиксный оператор) и следующим за ним именем,
+///  вызовом функции, открывающей скобкой или префиксным оператором без бинарного варианта вставляется
+///  оператор умножения: `2(3+4)` = `2*(3+4)`, `2√4` = `2*√4`. Так же умножается число после закрывающей скобки:
+///  `(2)3` = `(2)*3`
+/// Неявное умножение имеет приоритет обычного умножения: `1/2x` = `(1/2)*x`
+/// Вставленный токен имеет нулевую длину и располагается перед правым операндом
+///
+pub fn check_for_implicit_multiplication(list: &mut TokenList) -> Result<(), CalcError> {
+    let mut ind: usize = 1;
+
+    while ind < list.len() {
+        let prev: &Token = &list[ind - 1];
+        let tok: &Token = &list[ind];
+        let after_operand = prev.0.is_number()
+            || prev.0 == TokenType::Identifier
+            || prev.0 == TokenType::Constant
+            || prev.0 == TokenType::ClosedParenthesis
+            || prev.0 == TokenType::PostfixOperator;
+        let before_operand = tok.0 == TokenType::Identifier
+            || tok.0 == TokenType::Constant
+            || tok.0 == TokenType::Function
+            || tok.0 == TokenType::OpenedParenthesis
+            || tok.0 == TokenType::PrefixOperator
+            || (tok.0.is_number() && prev.0 == TokenType::ClosedParenthesis);
+
+        if after_operand && before_operand {
+            let pos = tok.2.start;
+            list.insert(ind, (TokenType::BinaryOperator, "*".to_string(), Span::new(pos, pos)));
+            ind += 1;
+        }
+        ind += 1;
+    }
+
+    Ok(())
+}
+
+// Операторы, после правого операнда которых неявное умножение читается неоднозначно: `1/2x`, `2^3x`
+const AMBIGUOUS_BEFORE_IMPLICIT: [&str; 4] = ["/", "%", "mod", "^"];
+
+///
+/// Вспомогательные функции передаваемые объекту валидатору для предупреждений
+/// Неявное умножение, левый операнд которого является правым операндом деления, остатка
+///  или возведения в степень, выполняется после этого оператора, что может не соответствовать ожиданиям
+///
+pub fn warn_ambiguous_implicit_multiplication(list: &TokenList) -> Vec<(String, Span)> {
+    let mut warnings: Vec<(String, Span)> = Vec::new();
+
+    for (ind, tok) in list.iter().enumerate() {
+        if tok.0 != TokenType::BinaryOperator || tok.2.start != tok.2.end || ind == 0 || ind + 1 == list.len() {
+            continue;
+        }
+
+        let start = operand_start(list, ind - 1);
+        if start < 2 {
+            continue;
+        }
+        let op: &Token = &list[start - 1];
+        if op.0 == TokenType::BinaryOperator && AMBIGUOUS_BEFORE_IMPLICIT.contains(&op.1.as_str()) {
+            let first = operand_start(list, start - 2);
+            let last = operand_end(list, ind + 1);
+            warnings.push((
+                format!("неявное умножение выполняется после оператора '{}' слева направо", op.1),
+                list[first].2.join(list[last].2),
+            ));
+        }
+    }
+
+    warnings
+}
+
+// Индекс первого токена операнда, заканчивающегося токеном с индексом `last`:
+//   выражения в скобках вместе с именем функции перед ними, либо одиночного токена, и унарных операторов перед ними
+//   Постфиксные операторы относятся к операнду
+fn operand_start(list: &TokenList, last: usize) -> usize {
+    let mut start = last;
+    while start > 0 && list[start].0 == TokenType::PostfixOperator {
+        start -= 1;
+    }
+    if list[start].0 == TokenType::ClosedParenthesis {
+        let mut depth: usize = 0;
+        loop {
+            match list[start].0 {
+                TokenType::ClosedParenthesis => depth += 1,
+                TokenType::OpenedParenthesis => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 || start == 0 {
+                break;
+            }
+            start -= 1;
+        }
+        if start > 0 && list[start - 1].0 == TokenType::Function {
+            start -= 1;
+        }
+    }
+    while start > 0 && list[start - 1].0.is_unary() {
+        start -= 1;
+    }
+
+    start
+}
+
+// Индекс последнего токена операнда, начинающегося токеном с индексом `first`
+fn operand_end(list: &TokenList, first: usize) -> usize {
+    let mut end = first;
+    while end + 1 < list.len() && (list[end].0.is_unary() || list[end].0 == TokenType::Function) {
+        end += 1;
+    }
+    if list[end].0 == TokenType::OpenedParenthesis {
+        let mut depth: usize = 0;
+        loop {
+            match list[end].0 {
+                TokenType::OpenedParenthesis => depth += 1,
+                TokenType::ClosedParenthesis => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 || end + 1 == list.len() {
+                break;
+            }
+            end += 1;
+        }
+    }
+    while end + 1 < list.len() && list[end + 1].0 == TokenType::PostfixOperator {
+        end += 1;
+    }
+
+    end
+}
+
+// Базовые тесты
+#[cfg(test)]
+use crate::lexer::{Lexer, RegexpLexer};
+
+#[test]
+fn test_implicit_multiplication() {
+    let validator = implicit_multiplication_validator();
+    let lex = RegexpLexer::new();
+    let validate = |input: &str| validator.validate(lex.tokenize(input).unwrap()).unwrap();
+    let implicit = |list: &TokenList| list.iter().filter(|tok| tok.2.start == tok.2.end).count();
+
+    assert_eq!(implicit(&validate("2(3+4)")), 1);
+    assert_eq!(implicit(&validate("(1+2)(3+4)")), 1);
+    assert_eq!(implicit(&validate("2pi x")), 2);
+    assert_eq!(implicit(&validate("3sin(x) - 2")), 1);
+    assert_eq!(implicit(&validate("sin(x) + 2 3")), 0);
+    assert_eq!(implicit(&validate("(2)3")), 1);
+    assert_eq!(implicit(&validate("(1+2)(3+4)5")), 2);
+
+    let kinds: Vec<TokenType> = validate("2√4 - ~1").into_iter().map(|tok| tok.0).collect();
+    assert_eq!(kinds[1..4], [TokenType::BinaryOperator, TokenType::PrefixOperator, TokenType::NumberInt]);
+    assert_eq!(kinds[5..], [TokenType::PrefixOperator, TokenType::NumberInt]);
+    assert_eq!(implicit(&validate("2 - √4")), 0);
+
+    // префиксный оператор, зарегистрированный в реестре, также умножается неявно
+    let mut registry = crate::registry::OperatorRegistry::standard();
+    registry.add_operator(crate::registry::OperatorDef::unary("¬", "NEGATE", 2, crate::Number::neg)).unwrap();
+    let custom = RegexpLexer::with_registry(&registry);
+    assert_eq!(implicit(&validator.validate(custom.tokenize("2¬8").unwrap()).unwrap()), 1);
+
+    assert_eq!(validator.warnings(&validate("1/2x")), vec![(
+        "неявное умножение выполняется после оператора '/' слева направо".to_string(),
+        Span::new(0, 4),
+    )]);
+    assert!(validator.warnings(&validate("2^-sin(x)(1+2)")).len() == 1);
+    assert!(validator.warnings(&validate("1/2 * x + 2x")).is_empty());
+}
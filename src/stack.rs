@@ -5,6 +5,13 @@ pub struct Stack<T> {
     pub stack: Vec<T>,
 }
 
+// Имплементация типажа для возможности создания объекта со значениями по-умолчанию
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Реализация методов стека
 impl<T> Stack<T> {
     pub fn new() -> Self {
@@ -3,17 +3,104 @@
 pub enum TokenType {
     NumberInt,
     NumberFloat,
+    NumberImaginary,
+    NumberRadix(u32), // целый литерал в системе счисления с заданным основанием (0xFF, 0b1010, 0o17)
     UnaryOperator,
+    PrefixOperator, // унарный оператор без бинарного варианта: `√x`, `~x`, `!x`
     BinaryOperator,
+    PostfixOperator, // оператор после операнда: `5!`, `10%`
     Function,
+    Boolean, // логический литерал true, false
+    Identifier,
+    Constant, // имя константы из реестра (pi, e, c, ...)
+    Assignment,
     OpenedParenthesis,
     ClosedParenthesis,
+    QuestionMark, // условие тернарного оператора `условие ? a : b`
+    Colon,        // разделитель ветвей тернарного оператора
     ArgumentSeparator,
     Whitespaces,
 }
 
-// Псевдоним кортежа для удобства работы - (Тип токена, "символьное представление")
-pub type Token = (TokenType, String);
+// Реализация методов для типа токена
+impl TokenType {
+    ///
+    /// Является ли токен числовым литералом
+    ///
+    pub fn is_number(&self) -> bool {
+        matches!(
+            self,
+            TokenType::NumberInt | TokenType::NumberFloat | TokenType::NumberImaginary | TokenType::NumberRadix(_)
+        )
+    }
+
+    ///
+    /// Является ли токен унарным оператором перед операндом
+    ///
+    pub fn is_unary(&self) -> bool {
+        matches!(self, TokenType::UnaryOperator | TokenType::PrefixOperator)
+    }
+}
+
+///
+/// Основание системы счисления, в которой записаны числовые литералы выражения
+/// Возвращает основание, если все литералы не в десятичной системе записаны в одной и той же системе счисления
+///
+pub fn literal_radix(tokens: &[Token]) -> Option<u32> {
+    let mut radixes = tokens.iter().filter_map(|tok| match tok.0 {
+        TokenType::NumberRadix(radix) => Some(radix),
+        _ => None,
+    });
+
+    let first = radixes.next()?;
+    if radixes.all(|radix| radix == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+///
+/// Положение фрагмента во входной строке: смещения в байтах [start, end)
+///
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+// Реализация методов для положения фрагмента
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    ///
+    /// Объединение двух фрагментов в один, покрывающий оба
+    ///
+    pub fn join(&self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+// Псевдоним кортежа для удобства работы - (Тип токена, "символьное представление", положение во входной строке)
+pub type Token = (TokenType, String, Span);
 
 // Псевдоним для краткости записи
 pub type TokenList = Vec<Token>;
+
+///
+/// Построение списка токенов, следующих во входной строке друг за другом без пробелов
+/// Используется в тестах
+///
+#[cfg(test)]
+pub fn token_list(list: &[(TokenType, &str)]) -> TokenList {
+    let mut pos: usize = 0;
+    list.iter()
+        .map(|(t, s)| {
+            let span = Span::new(pos, pos + s.len());
+            pos = span.end;
+            (*t, s.to_string(), span)
+        })
+        .collect()
+}
@@ -0,0 +1,133 @@
+//!
+//! Пошаговая трассировка преобразования и вычисления выражения
+//!
+
+// Псевдоним кортежа шага преобразования - (прочитанный токен, действие, стек операторов, выходная очередь)
+pub type ConversionStep = (String, String, String, String);
+
+// Псевдоним кортежа шага вычисления - (элемент выражения, стек аргументов до, стек аргументов после)
+pub type EvaluationStep = (String, String, String);
+
+///
+/// Журнал шагов алгоритма сортировочной станции и вычисления выражения
+/// Состояния стеков и очередей сохраняются в текстовом виде на момент выполнения шага
+///
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub conversion: Vec<ConversionStep>,
+    pub evaluation: Vec<EvaluationStep>,
+    arguments: Vec<String>, // текущее состояние стека аргументов при вычислении
+}
+
+// Реализация методов журнала трассировки
+impl Trace {
+    pub fn new() -> Self {
+        Trace::default()
+    }
+
+    ///
+    /// Запись шага преобразования
+    ///
+    pub fn conversion_step(&mut self, token: &str, action: String, stack: String, output: String) {
+        self.conversion.push((token.to_string(), action, stack, output));
+    }
+
+    ///
+    /// Запись шага вычисления: элемент выражения забирает из стека аргументов
+    ///   `arity` значений и кладет в стек результат
+    ///
+    pub fn evaluation_step(&mut self, item: &str, arity: usize, result: String) {
+        let before = self.arguments.join(" ");
+        let rest = self.arguments.len().saturating_sub(arity);
+        self.arguments.truncate(rest);
+        self.arguments.push(result);
+
+        self.evaluation
+            .push((item.to_string(), before, self.arguments.join(" ")));
+    }
+
+    ///
+    /// Представление журнала в виде двух таблиц: шаги преобразования и шаги вычисления
+    ///
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        if !self.conversion.is_empty() {
+            let rows: Vec<Vec<&str>> = self
+                .conversion
+                .iter()
+                .map(|(t, a, s, o)| vec![t.as_str(), a.as_str(), s.as_str(), o.as_str()])
+                .collect();
+            output.push_str("Преобразование в обратную польскую нотацию:\n");
+            output.push_str(&render_table(&["Токен", "Действие", "Стек операторов", "Выходная очередь"], &rows));
+        }
+
+        if !self.evaluation.is_empty() {
+            let rows: Vec<Vec<&str>> = self
+                .evaluation
+                .iter()
+                .map(|(i, b, a)| vec![i.as_str(), b.as_str(), a.as_str()])
+                .collect();
+            output.push_str("Вычисление:\n");
+            output.push_str(&render_table(&["Элемент", "Стек до", "Стек после"], &rows));
+        }
+
+        output
+    }
+}
+
+///
+/// Формирование текстовой таблицы с выравниванием столбцов по самому широкому значению
+/// Ширина значений считается в символах, а не в байтах
+///
+pub fn render_table(header: &[&str], rows: &[Vec<&str>]) -> String {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (ind, cell) in row.iter().enumerate() {
+            widths[ind] = widths[ind].max(cell.chars().count());
+        }
+    }
+
+    let line = |cells: &[&str]| -> String {
+        let formatted: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.chars().count())))
+            .collect();
+        format!("| {} |\n", formatted.join(" | "))
+    };
+
+    let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+
+    let mut output = line(header);
+    output.push_str(&format!("|-{}-|\n", separator.join("-|-")));
+    for row in rows {
+        output.push_str(&line(row));
+    }
+
+    output
+}
+
+// Базовые тесты
+#[cfg(test)]
+#[test]
+fn test_render_table() {
+    let rows = vec![vec!["1", "в очередь"], vec!["×", "в стек"]];
+    let expected = "| Токен | Действие  |\n|-------|-----------|\n| 1     | в очередь |\n| ×     | в стек    |\n";
+
+    assert_eq!(render_table(&["Токен", "Действие"], &rows), expected);
+}
+
+#[test]
+fn test_evaluation_steps() {
+    // 2 3 + => стек аргументов: [] -> [2] -> [2 3] -> [5]
+    let mut trace = Trace::new();
+    trace.evaluation_step("2", 0, "2".to_string());
+    trace.evaluation_step("3", 0, "3".to_string());
+    trace.evaluation_step("+", 2, "5".to_string());
+
+    assert_eq!(
+        trace.evaluation[2],
+        ("+".to_string(), "2 3".to_string(), "5".to_string())
+    );
+}
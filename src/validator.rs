@@ -1,38 +1,56 @@
-use crate::token::TokenList;
+use crate::error::CalcError;
+use crate::token::{Span, TokenList};
 
 ///
 /// Псевдоним для сигнатуры функций правил
 /// Все добавляемые в валидатор функции-правила должны соответствовать этой сигнатуре
+/// В случае нарушения правила функция возвращает ошибку с положением ошибочного токена
 ///
-pub type Rule = fn(&mut TokenList) -> bool;
+pub type Rule = fn(&mut TokenList) -> Result<(), CalcError>;
+
+///
+/// Псевдоним для сигнатуры функций предупреждений
+/// Функция-предупреждение не изменяет проверенный список токенов и возвращает описания
+///   неоднозначных фрагментов выражения вместе с их положением
+///
+pub type Warning = fn(&TokenList) -> Vec<(String, Span)>;
 
 ///
 /// Объект валидатор
 ///
 pub struct Validator {
     rules: Vec<Rule>,
+    warnings: Vec<Warning>,
+}
+
+// Имплементация типажа для возможности создания объекта со значениями по-умолчанию
+impl Default for Validator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 // Реализация методов валидатора
 impl Validator {
     pub fn new() -> Self {
-        Self { rules: Vec::new() }
+        Self {
+            rules: Vec::new(),
+            warnings: Vec::new(),
+        }
     }
 
     ///
     /// Провести валидацию списка токенов
     /// Входной список токенов клонируется и после проверки возвращается новый список токенов
     ///
-    pub fn validate(&self, input: TokenList) -> Result<TokenList, &str> {
+    pub fn validate(&self, input: TokenList) -> Result<TokenList, CalcError> {
         if self.rules.is_empty() {
             return Ok(input);
         }
 
         let mut output: TokenList = input.clone();
         for rule in &self.rules {
-            if !rule(&mut output) {
-                return Err("Ошибка валидации");
-            }
+            rule(&mut output)?;
         }
 
         Ok(output)
@@ -45,4 +63,19 @@ impl Validator {
         self.rules.push(r);
         self
     }
+
+    ///
+    /// Предупреждения о неоднозначных фрагментах проверенного списка токенов
+    ///
+    pub fn warnings(&self, input: &TokenList) -> Vec<(String, Span)> {
+        self.warnings.iter().flat_map(|warning| warning(input)).collect()
+    }
+
+    ///
+    /// Добавить функцию-предупреждение к списку проверок
+    ///
+    pub fn add_warning(&mut self, w: Warning) -> &mut Self {
+        self.warnings.push(w);
+        self
+    }
 }
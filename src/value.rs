@@ -0,0 +1,140 @@
+//!
+//! Значение выражения: число, либо логическое значение
+//!
+use std::cmp::Ordering;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::number::{Number, NumberFormat};
+
+///
+/// Значение, получаемое при вычислении выражения
+///   - Number - число выбранной числовой системы
+///   - Bool   - логическое значение: литералы `true`, `false`, результаты сравнений и логических операторов
+///
+/// Арифметические операторы и функции принимают только числа, логические операторы - только логические значения.
+/// Смешение типов является ошибкой вычисления
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(Number),
+    Bool(bool),
+}
+
+// Псевдонимы для сигнатур операторов над значениями
+pub type UnaryValueFunction = Rc<dyn Fn(Value) -> Result<Value, String>>;
+pub type BinaryValueFunction = Rc<dyn Fn((Value, Value)) -> Result<Value, String>>;
+
+// Реализация методов значения
+impl Value {
+    ///
+    /// Число, хранящееся в значении. Для логического значения возвращается ошибка типа
+    ///
+    pub fn number(self) -> Result<Number, String> {
+        match self {
+            Value::Number(v) => Ok(v),
+            Value::Bool(b) => Err(format!("ожидается число, получено логическое значение {}", b)),
+        }
+    }
+
+    ///
+    /// Логическое значение. Для числа возвращается ошибка типа
+    ///
+    pub fn boolean(self) -> Result<bool, String> {
+        match self {
+            Value::Bool(b) => Ok(b),
+            Value::Number(v) => Err(format!("ожидается логическое значение, получено число {}", v)),
+        }
+    }
+
+    ///
+    /// Логическое отрицание
+    ///
+    pub fn logical_not(self) -> Result<Value, String> {
+        Ok(Value::Bool(!self.boolean()?))
+    }
+
+    ///
+    /// Логическое И
+    ///
+    pub fn and(self, rhs: Value) -> Result<Value, String> {
+        Ok(Value::Bool(self.boolean()? & rhs.boolean()?))
+    }
+
+    ///
+    /// Логическое ИЛИ
+    ///
+    pub fn or(self, rhs: Value) -> Result<Value, String> {
+        Ok(Value::Bool(self.boolean()? | rhs.boolean()?))
+    }
+
+    ///
+    /// Проверка на равенство. Сравниваются числа с числами и логические значения с логическими
+    ///
+    pub fn equals(self, rhs: Value) -> Result<bool, String> {
+        match (self, rhs) {
+            (Value::Number(x), Value::Number(y)) => Ok(x.equals(&y)),
+            (Value::Bool(x), Value::Bool(y)) => Ok(x == y),
+            (x, y) => Err(format!("нельзя сравнить значения разных типов: {} и {}", x, y)),
+        }
+    }
+
+    ///
+    /// Сравнение на больше/меньше. Определено только для вещественных чисел
+    ///
+    pub fn compare(self, rhs: Value) -> Result<Ordering, String> {
+        let (x, y) = (self.number()?, rhs.number()?);
+        x.compare(&y)
+            .ok_or_else(|| format!("комплексные числа {} и {} нельзя сравнить на больше/меньше", x, y))
+    }
+
+    ///
+    /// Текстовое представление значения в соответствии с настройками вывода чисел
+    ///
+    pub fn format(&self, format: &NumberFormat) -> String {
+        match self {
+            Value::Number(v) => format.format(v),
+            Value::Bool(b) => b.to_string(),
+        }
+    }
+}
+
+// Число является значением
+impl From<Number> for Value {
+    fn from(v: Number) -> Self {
+        Value::Number(v)
+    }
+}
+
+// Логическое значение
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+// Печать значения: числа в представлении своей числовой системы, логические значения - `true`, `false`
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(v) => write!(f, "{}", v),
+            Value::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+// Базовые тесты
+#[cfg(test)]
+#[test]
+fn test_value_types() {
+    let number = |s: &str| Value::Number(Number::parse(s).unwrap());
+
+    assert_eq!(number("2").compare(number("3")), Ok(Ordering::Less));
+    assert_eq!(number("0.5").equals(Value::Number(Number::Float(0.5))), Ok(true));
+    assert_eq!(Value::Bool(true).and(Value::Bool(false)), Ok(Value::Bool(false)));
+
+    assert!(Value::Bool(true).number().is_err());
+    assert!(number("1").logical_not().is_err());
+    assert!(number("1").equals(Value::Bool(true)).is_err());
+    assert!(Value::Number(Number::Complex(num_complex::Complex64::new(1.0, 1.0))).compare(number("1")).is_err());
+}
@@ -1,19 +1,115 @@
 extern crate termion;
+use std::io;
+use std::io::Write;
+use std::rc::Rc;
 use termion::color::Color;
 use termion::{color, style};
 
+use num_bigint::BigInt;
+use num_traits::One;
+
+use crate::error::CalcError;
+use crate::number::NumberFormat;
+use crate::trace::Trace;
+use crate::value::Value;
+
 ///
 /// Типаж для определения объекта выводящего результаты и ошибки/предупреждения
 ///
+#[allow(dead_code)]
 pub trait Writer {
     fn write(&self, output: &[u8]) -> Result<usize, &str>;
     fn print_error(&self, output: String);
     fn print_warninig(&self, output: String);
     fn print_success(&self, output: String);
+
+    ///
+    /// Вывод ошибки вместе с входной строкой, в которой подчеркнут ошибочный фрагмент
+    ///
+    fn print_calc_error(&self, input: &str, error: &CalcError) {
+        self.print_error(render_error(input, error));
+    }
+
+    ///
+    /// Вывод журнала трассировки преобразования и вычисления в виде таблиц
+    ///
+    fn print_trace(&self, trace: &Trace) {
+        let _ = self.write(trace.render().as_bytes());
+    }
+
+    ///
+    /// Вывод результата вычисления выражения. Для присваивания передается имя переменной
+    /// Текстовое представление результата определяется настройками format
+    ///
+    fn print_result(&self, name: Option<&str>, value: &Value, format: &NumberFormat) {
+        match name {
+            Some(name) => self.print_success(format!("{} = {}", name, value.format(format))),
+            None => self.print_success(format!("Результат выражения: {}", value.format(format))),
+        }
+    }
+
+    ///
+    /// Вывод результата вместе с его представлениями (например, в других системах счисления)
+    /// Представления печатаются после результата строками вида `имя: значение`
+    ///
+    fn print_result_views(&self, name: Option<&str>, value: &Value, format: &NumberFormat, views: &[(&str, String)]) {
+        self.print_result(name, value, format);
+        for (label, view) in views {
+            self.print_success(format!("{}: {}", label, view));
+        }
+    }
+
+    ///
+    /// Вывод выражения в обратной польской нотации, когда оно является результатом работы
+    ///
+    fn print_rpn(&self, rpn: &str) {
+        self.print_success(rpn.to_string());
+    }
+
+    ///
+    /// Вывод вспомогательной информации о ходе вычисления
+    ///
+    fn print_info(&self, output: String) {
+        let _ = self.write(format!("{}\n", output).as_bytes());
+    }
+}
+
+///
+/// Формирование текста ошибки с подчеркиванием ошибочного фрагмента входной строки
+/// Положение фрагмента задается в байтах, подчеркивание выполняется посимвольно
+///
+pub fn render_error(input: &str, error: &CalcError) -> String {
+    let span = match error.span() {
+        Some(span) => span,
+        None => return error.to_string(),
+    };
+
+    let start = span.start.min(input.len());
+    let end = span.end.clamp(start, input.len());
+    let column = input.get(..start).map_or(0, |s| s.chars().count());
+    let width = input.get(start..end).map_or(0, |s| s.chars().count()).max(1);
+
+    format!(
+        "{}\n{}\n{}{}",
+        error,
+        input,
+        " ".repeat(column),
+        "^".repeat(width)
+    )
+}
+
+// Вывод данных в стандартный поток вывода без оформления
+fn write_stdout(output: &[u8]) -> Result<usize, &'static str> {
+    let mut stdout = io::stdout();
+    match stdout.write_all(output).and_then(|_| stdout.flush()) {
+        Ok(_) => Ok(output.len()),
+        Err(_) => Err("Не удалось вывести данные"),
+    }
 }
 
 ///
 /// Объект для реализации вывода в стандартный поток вывода
+#[allow(dead_code)]
 pub struct ConsoleOutput<'a> {
     pub error_color: &'a dyn Color,
     pub warning_color: &'a dyn Color,
@@ -33,8 +129,8 @@ impl Default for ConsoleOutput<'_> {
 
 // Имплементация типажа Writer
 impl Writer for ConsoleOutput<'_> {
-    fn write(&self, _: &[u8]) -> Result<usize, &str> {
-        Ok(0)
+    fn write(&self, output: &[u8]) -> Result<usize, &str> {
+        write_stdout(output)
     }
 
     fn print_error(&self, s: String) {
@@ -49,3 +145,350 @@ impl Writer for ConsoleOutput<'_> {
         println!("{}{}{}", color::Fg(self.success_color), s, style::Reset);
     }
 }
+
+///
+/// Объект для вывода без оформления: в стандартный поток вывода печатаются только результаты,
+///   ошибки и предупреждения печатаются в стандартный поток ошибок
+/// Предназначен для использования в сценариях
+///
+pub struct PlainOutput;
+
+// Имплементация типажа Writer
+impl Writer for PlainOutput {
+    fn write(&self, output: &[u8]) -> Result<usize, &str> {
+        write_stdout(output)
+    }
+
+    fn print_error(&self, s: String) {
+        eprintln!("{}", s);
+    }
+
+    fn print_warninig(&self, s: String) {
+        eprintln!("{}", s);
+    }
+
+    fn print_success(&self, s: String) {
+        println!("{}", s);
+    }
+
+    fn print_result(&self, _: Option<&str>, value: &Value, format: &NumberFormat) {
+        println!("{}", value.format(format));
+    }
+
+    fn print_info(&self, _: String) {}
+}
+
+///
+/// Объект для вывода в формате JSON: каждый результат или ошибка печатается отдельным объектом в одной строке
+///
+pub struct JsonOutput;
+
+// Имплементация типажа Writer
+impl Writer for JsonOutput {
+    fn write(&self, output: &[u8]) -> Result<usize, &str> {
+        write_stdout(output)
+    }
+
+    fn print_error(&self, s: String) {
+        println!("{{\"error\": {}}}", json_string(&s));
+    }
+
+    fn print_warninig(&self, s: String) {
+        println!("{{\"warning\": {}}}", json_string(&s));
+    }
+
+    fn print_success(&self, s: String) {
+        println!("{{\"message\": {}}}", json_string(&s));
+    }
+
+    fn print_calc_error(&self, _: &str, error: &CalcError) {
+        match error.span() {
+            Some(span) => println!(
+                "{{\"error\": {}, \"start\": {}, \"end\": {}}}",
+                json_string(&error.to_string()),
+                span.start,
+                span.end
+            ),
+            None => self.print_error(error.to_string()),
+        }
+    }
+
+    fn print_result(&self, name: Option<&str>, value: &Value, format: &NumberFormat) {
+        println!("{}", json_result(name, value, format, &[]));
+    }
+
+    fn print_result_views(&self, name: Option<&str>, value: &Value, format: &NumberFormat, views: &[(&str, String)]) {
+        println!("{}", json_result(name, value, format, views));
+    }
+
+    fn print_rpn(&self, rpn: &str) {
+        println!("{{\"rpn\": {}}}", json_string(rpn));
+    }
+
+    fn print_info(&self, _: String) {}
+}
+
+// Объект JSON с результатом: имя переменной, если это присваивание, значение и вложенный объект
+//   с представлениями значения
+fn json_result(name: Option<&str>, value: &Value, format: &NumberFormat, views: &[(&str, String)]) -> String {
+    let mut fields: Vec<String> = Vec::new();
+    if let Some(name) = name {
+        fields.push(format!("\"variable\": {}", json_string(name)));
+    }
+    fields.push(format!("\"result\": {}", json_string(&value.format(format))));
+    if !views.is_empty() {
+        let views: Vec<String> =
+            views.iter().map(|(label, view)| format!("{}: {}", json_string(label), json_string(view))).collect();
+        fields.push(format!("\"views\": {{{}}}", views.join(", ")));
+    }
+
+    format!("{{{}}}", fields.join(", "))
+}
+
+///
+/// Размер машинного слова для вывода в режиме программиста
+///
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WordSize {
+    Bits8,
+    Bits16,
+    Bits32,
+    Bits64,
+}
+
+// Реализация методов размера машинного слова
+impl WordSize {
+    ///
+    /// Получение размера слова по количеству бит: 8, 16, 32, 64
+    ///
+    pub fn parse(bits: &str) -> Option<WordSize> {
+        match bits {
+            "8" => Some(WordSize::Bits8),
+            "16" => Some(WordSize::Bits16),
+            "32" => Some(WordSize::Bits32),
+            "64" => Some(WordSize::Bits64),
+            _ => None,
+        }
+    }
+
+    pub fn bits(&self) -> usize {
+        match self {
+            WordSize::Bits8 => 8,
+            WordSize::Bits16 => 16,
+            WordSize::Bits32 => 32,
+            WordSize::Bits64 => 64,
+        }
+    }
+}
+
+// Псевдоним кортежа представления целого числа в машинном слове -
+//   (десятичное значение, шестнадцатеричная, восьмеричная и двоичная запись, помещается ли число в слово)
+pub type WordView = (BigInt, String, String, String, bool);
+
+///
+/// Вывод в режиме программиста: вместе с целым результатом печатается содержимое машинного слова заданного размера
+///   в десятичной, шестнадцатеричной, восьмеричной и двоичной системах счисления.
+/// Отрицательные числа представляются в дополнительном коде, десятичное значение слова
+///   интерпретируется как знаковое или беззнаковое число
+/// Остальной вывод, как и вывод нецелых результатов, выполняется оборачиваемым объектом
+///
+pub struct ProgrammerOutput {
+    inner: Rc<dyn Writer>,
+    word: WordSize,
+    signed: bool,
+}
+
+// Реализация методов вывода в режиме программиста
+impl ProgrammerOutput {
+    pub fn new(inner: Rc<dyn Writer>, word: WordSize, signed: bool) -> Self {
+        ProgrammerOutput { inner, word, signed }
+    }
+
+    ///
+    /// Представление целого числа в машинном слове
+    /// Если число не помещается в слово, то используются младшие разряды его дополнительного кода
+    ///
+    pub fn view(&self, value: &BigInt) -> WordView {
+        let bits = self.word.bits();
+        let modulus = BigInt::one() << bits;
+        let half = BigInt::one() << (bits - 1);
+
+        // содержимое слова: младшие разряды дополнительного кода
+        let word: BigInt = value & (&modulus - 1);
+        let (decimal, fits) = if self.signed {
+            let decimal = if word >= half { &word - &modulus } else { word.clone() };
+            (decimal, -&half <= *value && *value < half)
+        } else {
+            (word.clone(), *value >= BigInt::from(0) && *value < modulus)
+        };
+
+        let hex = format!("0x{:0>width$}", word.to_str_radix(16).to_uppercase(), width = bits / 4);
+        let oct = format!("0o{}", word.to_str_radix(8));
+        let binary = format!("{:0>width$}", word.to_str_radix(2), width = bits);
+        let groups: Vec<&str> = (0..bits).step_by(4).map(|ind| &binary[ind..ind + 4]).collect();
+        let bin = format!("0b{}", groups.join("_"));
+
+        (decimal, hex, oct, bin, fits)
+    }
+}
+
+// Имплементация типажа Writer: вывод делегируется оборачиваемому объекту
+impl Writer for ProgrammerOutput {
+    fn write(&self, output: &[u8]) -> Result<usize, &str> {
+        self.inner.write(output)
+    }
+
+    fn print_error(&self, s: String) {
+        self.inner.print_error(s);
+    }
+
+    fn print_warninig(&self, s: String) {
+        self.inner.print_warninig(s);
+    }
+
+    fn print_success(&self, s: String) {
+        self.inner.print_success(s);
+    }
+
+    fn print_calc_error(&self, input: &str, error: &CalcError) {
+        self.inner.print_calc_error(input, error);
+    }
+
+    fn print_trace(&self, trace: &Trace) {
+        self.inner.print_trace(trace);
+    }
+
+    fn print_result(&self, name: Option<&str>, value: &Value, format: &NumberFormat) {
+        let integer = match value {
+            Value::Number(number) => number.to_integer(),
+            Value::Bool(_) => Err(String::new()),
+        };
+        let integer = match integer {
+            Ok(integer) => integer,
+            Err(_) => return self.inner.print_result(name, value, format),
+        };
+
+        let (decimal, hex, oct, bin, fits) = self.view(&integer);
+        if !fits {
+            self.inner.print_warninig(format!(
+                "значение {} не помещается в {}-битное {} слово, показаны младшие разряды дополнительного кода",
+                integer,
+                self.word.bits(),
+                if self.signed { "знаковое" } else { "беззнаковое" }
+            ));
+        }
+
+        let format = NumberFormat {
+            radix: None,
+            ..format.clone()
+        };
+        let views = [("dec", decimal.to_string()), ("hex", hex), ("oct", oct), ("bin", bin)];
+        self.inner.print_result_views(name, value, &format, &views);
+    }
+
+    fn print_result_views(&self, name: Option<&str>, value: &Value, format: &NumberFormat, views: &[(&str, String)]) {
+        self.inner.print_result_views(name, value, format, views);
+    }
+
+    fn print_rpn(&self, rpn: &str) {
+        self.inner.print_rpn(rpn);
+    }
+
+    fn print_info(&self, output: String) {
+        self.inner.print_info(output);
+    }
+}
+
+///
+/// Объект, подавляющий любой вывод. Используется при вычислении выражений из кода
+///
+pub struct SilentOutput;
+
+// Имплементация типажа Writer
+impl Writer for SilentOutput {
+    fn write(&self, output: &[u8]) -> Result<usize, &str> {
+        Ok(output.len())
+    }
+
+    fn print_error(&self, _: String) {}
+
+    fn print_warninig(&self, _: String) {}
+
+    fn print_success(&self, _: String) {}
+}
+
+///
+/// Представление строки в виде строкового литерала JSON
+///
+pub fn json_string(s: &str) -> String {
+    let mut output = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+// Базовые тесты
+#[cfg(test)]
+use crate::number::Number;
+#[cfg(test)]
+use crate::token::Span;
+
+#[test]
+fn test_render_error_underline() {
+    let error = CalcError::Evaluation("деление на ноль".to_string(), Span::new(4, 5));
+    let expected = "Ошибка вычисления: деление на ноль\n1 + 1/0\n    ^";
+
+    assert_eq!(render_error("1 + 1/0", &error), expected);
+}
+
+#[test]
+fn test_render_error_unicode() {
+    // символ '×' занимает во входной строке два байта
+    let error = CalcError::Lexical(Span::new(2, 4));
+    let expected = "Ошибка разбиения на лексемы: неизвестная лексема\n2 × 3\n  ^";
+
+    assert_eq!(render_error("2 × 3", &error), expected);
+}
+
+#[test]
+fn test_programmer_view() {
+    let signed = ProgrammerOutput::new(Rc::new(SilentOutput {}), WordSize::Bits8, true);
+    let (decimal, hex, oct, bin, fits) = signed.view(&BigInt::from(-1));
+    assert_eq!(
+        (decimal.to_string(), hex.as_str(), oct.as_str(), bin.as_str(), fits),
+        ("-1".to_string(), "0xFF", "0o377", "0b1111_1111", true)
+    );
+
+    // 200 не помещается в знаковый байт, но помещается в беззнаковый
+    assert_eq!(signed.view(&BigInt::from(200)).0, BigInt::from(-56));
+    assert!(!signed.view(&BigInt::from(200)).4);
+
+    let unsigned = ProgrammerOutput::new(Rc::new(SilentOutput {}), WordSize::Bits16, false);
+    let (decimal, hex, _, _, fits) = unsigned.view(&BigInt::from(-2));
+    assert_eq!((decimal, hex.as_str(), fits), (BigInt::from(65534), "0xFFFE", false));
+}
+
+#[test]
+fn test_json_result_views() {
+    let value = Value::Number(Number::Integer(BigInt::from(300)));
+    let views = [("dec", "44".to_string()), ("hex", "0x2C".to_string())];
+
+    assert_eq!(
+        json_result(Some("x"), &value, &NumberFormat::default(), &views),
+        r#"{"variable": "x", "result": "300", "views": {"dec": "44", "hex": "0x2C"}}"#
+    );
+    assert_eq!(json_result(None, &value, &NumberFormat::default(), &[]), r#"{"result": "300"}"#);
+}
+
+#[test]
+fn test_json_string() {
+    assert_eq!(json_string("1 \"2\"\n\\"), r#""1 \"2\"\n\\""#);
+}